@@ -0,0 +1,186 @@
+use hyper::{
+    body::HttpBody,
+    header,
+    service::{
+        make_service_fn,
+        service_fn,
+    },
+    Body,
+    Method,
+    Request,
+    Response,
+    Server,
+    StatusCode,
+};
+use serde::Deserialize;
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+};
+use crate::{
+    ApiConfig,
+    botmods::unicode::unicode_to_latex,
+    lint::{
+        lint,
+        Severity,
+    },
+    render::{
+        compile_svg,
+        rasterize,
+        MathText,
+        RenderError,
+        RenderTimings,
+        SCALE,
+        MIN_SCALE,
+        MAX_SCALE,
+    },
+};
+
+const MAX_BODY: usize = 16 * 1024;
+/// The longest message Discord allows with Nitro (2000 without), so the API can't ask for more than the bot ever compiles
+const MAX_SOURCE: usize = 4000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Png,
+    Svg,
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::Png
+    }
+}
+
+fn default_scale() -> u32 {
+    SCALE
+}
+
+#[derive(Deserialize)]
+struct RenderRequest {
+    #[serde(default)]
+    latex: Option<String>,
+    #[serde(default)]
+    asciimath: Option<String>,
+    #[serde(default)]
+    format: Format,
+    #[serde(default = "default_scale")]
+    scale: u32,
+    /// Skip swapping pasted Unicode math for LaTeX commands, like `--raw`
+    #[serde(default)]
+    raw: bool,
+}
+
+type ApiError = (StatusCode, String);
+
+/// Serves `POST /render` on localhost until the bot stops
+pub async fn serve(config: &'static ApiConfig) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], config.port));
+
+    let make_svc = make_service_fn(move |_| async move {
+        Ok::<_, Infallible>(service_fn(move |req| handle(config, req)))
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed starting the HTTP API on {}:\n{}", addr, e);
+            return
+        }
+    };
+
+    if let Err(e) = server.serve(make_svc).await {
+        eprintln!("HTTP API error: {}", e);
+    }
+}
+
+async fn handle(config: &'static ApiConfig, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    Ok(match render(config, req).await {
+        Ok(r) => r,
+        Err((status, e)) => {
+            Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!({ "error": e }).to_string()))
+                .unwrap()
+        },
+    })
+}
+
+async fn render(config: &ApiConfig, req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    if req.uri().path() != "/render" {
+        return Err((StatusCode::NOT_FOUND, String::from("Only /render is served")))
+    }
+    if req.method() != Method::POST {
+        return Err((StatusCode::METHOD_NOT_ALLOWED, String::from("Use POST")))
+    }
+
+    let key = req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if !key.map_or(false, |k| config.keys.iter().any(|c| c == k)) {
+        return Err((StatusCode::UNAUTHORIZED, String::from("Missing or unknown API key")))
+    }
+
+    // Read the body a chunk at a time so a huge one gets turned away early
+    let mut body = req.into_body();
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_BODY {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Request bodies are limited to {} bytes", MAX_BODY)))
+        }
+    }
+
+    let r: RenderRequest = serde_json::from_slice(&bytes)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Couldn't parse the request: {}", e)))?;
+
+    let source = match (r.latex, r.asciimath) {
+        (Some(l), None) if r.raw => MathText::Latex(l),
+        (Some(l), None) => MathText::Latex(unicode_to_latex(&l)),
+        (None, Some(a)) => MathText::AsciiMath(a),
+        _ => {return Err((StatusCode::BAD_REQUEST, String::from("Give exactly one of `latex` or `asciimath`")))}
+    };
+
+    if source.as_str().chars().count() > MAX_SOURCE {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Sources are limited to {} characters", MAX_SOURCE)))
+    }
+    if r.scale < MIN_SCALE || r.scale > MAX_SCALE {
+        return Err((StatusCode::BAD_REQUEST, format!("`scale` has to be between {} and {}", MIN_SCALE, MAX_SCALE)))
+    }
+
+    // Same as the bot, the linter's errors are turned away before latex sees them
+    if let MathText::Latex(s) = &source {
+        let errs: Vec<String> = lint(s).into_iter()
+            .filter(|l| l.severity == Severity::Error)
+            .map(|l| l.to_string())
+            .collect();
+        if !errs.is_empty() {
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, errs.join("\n")))
+        }
+    }
+
+    let mut timings = RenderTimings::default();
+    let svg = compile_svg(&source, &mut timings).await.map_err(|e| match e {
+        RenderError::MathError(m) => (StatusCode::UNPROCESSABLE_ENTITY, m),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    let (data, content_type) = match r.format {
+        Format::Svg => (svg, "image/svg+xml"),
+        Format::Png => {
+            let png = rasterize(&svg, r.scale, &mut timings)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (png, "image/png")
+        },
+    };
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header("X-Render-Cache", if timings.cached {"hit"} else {"miss"})
+        .header("X-Render-Timings", timings.footer())
+        .body(Body::from(data))
+        .unwrap())
+}
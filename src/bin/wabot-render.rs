@@ -0,0 +1,201 @@
+use std::{
+    env,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process,
+};
+use wabot::{
+    botmods::unicode::unicode_to_latex,
+    render::{
+        compile_pdf,
+        compile_svg,
+        rasterize,
+        MathText,
+        RenderTimings,
+        SCALE,
+        MIN_SCALE,
+        MAX_SCALE,
+    },
+};
+
+const USAGE: &str = "Renders math the same way the bot does, without Discord
+
+Usage: wabot-render [options] <expression>
+       wabot-render [options] -f <file>
+
+Options:
+  -f, --file <path>     Read the input from a file, `-` for stdin
+  -a, --ascii           The input is AsciiMath instead of LaTeX
+  -r, --raw             Don't swap pasted Unicode math for LaTeX commands
+      --svg             Write an SVG instead of a PNG
+      --pdf             Write a PDF with black text, LaTeX only
+  -s, --scale <n>       PNG scale, from 2 to 16, 8 by default
+  -o, --output <path>   Where to write the result, snippet.<format> by default
+  -l, --lines           Render each non-empty line on its own, as <output>-1, <output>-2, ...
+  -t, --timings         Print how long each stage took
+  -h, --help            Show this message";
+
+#[derive(PartialEq)]
+enum Format {
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl Format {
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Svg => "svg",
+            Format::Pdf => "pdf",
+        }
+    }
+}
+
+struct Options {
+    input: String,
+    ascii: bool,
+    raw: bool,
+    format: Format,
+    scale: u32,
+    output: Option<PathBuf>,
+    lines: bool,
+    timings: bool,
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}\n\nRun `wabot-render --help` for usage", msg);
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut args = env::args().skip(1);
+    let mut opts = Options {
+        input: String::new(),
+        ascii: false,
+        raw: false,
+        format: Format::Png,
+        scale: SCALE,
+        output: None,
+        lines: false,
+        timings: false,
+    };
+    let mut file: Option<String> = None;
+    let mut expression: Vec<String> = vec![];
+
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            "-f" | "--file" => file = Some(args.next().unwrap_or_else(|| fail("`--file` needs a path"))),
+            "-a" | "--ascii" => opts.ascii = true,
+            "-r" | "--raw" => opts.raw = true,
+            "--svg" => opts.format = Format::Svg,
+            "--pdf" => opts.format = Format::Pdf,
+            "-s" | "--scale" => {
+                opts.scale = match args.next().and_then(|s| s.parse().ok()) {
+                    Some(s) if (MIN_SCALE..=MAX_SCALE).contains(&s) => s,
+                    _ => fail(&format!("`--scale` needs a number from {} to {}", MIN_SCALE, MAX_SCALE)),
+                }
+            },
+            "-o" | "--output" => opts.output = Some(PathBuf::from(args.next().unwrap_or_else(|| fail("`--output` needs a path")))),
+            "-l" | "--lines" => opts.lines = true,
+            "-t" | "--timings" => opts.timings = true,
+            // Anything else is the expression, which is allowed to start with a `-`
+            _ => {
+                expression.push(a);
+                expression.extend(args.by_ref());
+            },
+        }
+    }
+
+    opts.input = match (file, expression.is_empty()) {
+        (Some(_), false) => fail("Give either an expression or `--file`, not both"),
+        (Some(f), true) if f == "-" => {
+            let mut s = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut s).unwrap_or_else(|e| fail(&format!("Couldn't read stdin: {}", e)));
+            s
+        },
+        (Some(f), true) => fs::read_to_string(&f).unwrap_or_else(|e| fail(&format!("Couldn't read `{}`: {}", f, e))),
+        (None, false) => expression.join(" "),
+        (None, true) => fail("Nothing to render"),
+    };
+
+    if opts.ascii && opts.format == Format::Pdf {
+        fail("AsciiMath can't be written as a PDF");
+    }
+
+    opts
+}
+
+/// `out.png` becomes `out-3.png` for the third line
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("snippet");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(e) => format!("{}-{}.{}", stem, n, e),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+async fn render(opts: &Options, input: &str, path: &Path) -> Result<(), String> {
+    let source = match (opts.ascii, opts.raw) {
+        (true, _) => MathText::AsciiMath(String::from(input)),
+        (false, true) => MathText::Latex(String::from(input)),
+        (false, false) => MathText::Latex(unicode_to_latex(input)),
+    };
+
+    let mut timings = RenderTimings::default();
+    let data = match opts.format {
+        Format::Pdf => compile_pdf(source.as_str()).await.map_err(|e| e.to_string())?,
+        Format::Svg => compile_svg(&source, &mut timings).await.map_err(|e| e.to_string())?,
+        Format::Png => {
+            let svg = compile_svg(&source, &mut timings).await.map_err(|e| e.to_string())?;
+            rasterize(&svg, opts.scale, &mut timings).map_err(|e| e.to_string())?
+        },
+    };
+
+    fs::write(path, data).map_err(|e| format!("Couldn't write `{}`: {}", path.display(), e))?;
+
+    if opts.timings && opts.format != Format::Pdf {
+        println!("{}: {}", path.display(), timings.footer());
+    } else {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let opts = parse_args();
+    let output = opts.output.clone().unwrap_or_else(|| PathBuf::from(format!("snippet.{}", opts.format.extension())));
+
+    let jobs: Vec<(String, PathBuf)> = if opts.lines {
+        opts.input.lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .enumerate()
+            .map(|(i, l)| (String::from(l), numbered(&output, i + 1)))
+            .collect()
+    } else {
+        vec![(opts.input.clone(), output)]
+    };
+
+    // Keep going past a bad line, a handout is mostly fine with one missing
+    let mut failed = false;
+    for (input, path) in &jobs {
+        if let Err(e) = render(&opts, input, path).await {
+            eprintln!("{} failed:\n{}", path.display(), e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
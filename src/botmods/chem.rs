@@ -0,0 +1,570 @@
+use regex::Regex;
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use crate::{
+    PREFIX,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::{
+            render_generated,
+        },
+        utils::BotModule,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_CHEM: BotModule = BotModule {
+        command_group: &CHEMISTRY_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}ce .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}molar .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}balance .*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Chemistry commands"]
+#[commands(ce, molar, balance)]
+struct Chemistry;
+
+/// Symbol, name and standard atomic weight (mass number of the most stable isotope when there is none)
+const ELEMENTS: [(&str, &str, f64); 118] = [
+    ("H", "Hydrogen", 1.008), ("He", "Helium", 4.0026), ("Li", "Lithium", 6.94),
+    ("Be", "Beryllium", 9.0122), ("B", "Boron", 10.81), ("C", "Carbon", 12.011),
+    ("N", "Nitrogen", 14.007), ("O", "Oxygen", 15.999), ("F", "Fluorine", 18.998),
+    ("Ne", "Neon", 20.180), ("Na", "Sodium", 22.990), ("Mg", "Magnesium", 24.305),
+    ("Al", "Aluminium", 26.982), ("Si", "Silicon", 28.085), ("P", "Phosphorus", 30.974),
+    ("S", "Sulfur", 32.06), ("Cl", "Chlorine", 35.45), ("Ar", "Argon", 39.95),
+    ("K", "Potassium", 39.098), ("Ca", "Calcium", 40.078), ("Sc", "Scandium", 44.956),
+    ("Ti", "Titanium", 47.867), ("V", "Vanadium", 50.942), ("Cr", "Chromium", 51.996),
+    ("Mn", "Manganese", 54.938), ("Fe", "Iron", 55.845), ("Co", "Cobalt", 58.933),
+    ("Ni", "Nickel", 58.693), ("Cu", "Copper", 63.546), ("Zn", "Zinc", 65.38),
+    ("Ga", "Gallium", 69.723), ("Ge", "Germanium", 72.630), ("As", "Arsenic", 74.922),
+    ("Se", "Selenium", 78.971), ("Br", "Bromine", 79.904), ("Kr", "Krypton", 83.798),
+    ("Rb", "Rubidium", 85.468), ("Sr", "Strontium", 87.62), ("Y", "Yttrium", 88.906),
+    ("Zr", "Zirconium", 91.224), ("Nb", "Niobium", 92.906), ("Mo", "Molybdenum", 95.95),
+    ("Tc", "Technetium", 98.0), ("Ru", "Ruthenium", 101.07), ("Rh", "Rhodium", 102.91),
+    ("Pd", "Palladium", 106.42), ("Ag", "Silver", 107.87), ("Cd", "Cadmium", 112.41),
+    ("In", "Indium", 114.82), ("Sn", "Tin", 118.71), ("Sb", "Antimony", 121.76),
+    ("Te", "Tellurium", 127.60), ("I", "Iodine", 126.90), ("Xe", "Xenon", 131.29),
+    ("Cs", "Caesium", 132.91), ("Ba", "Barium", 137.33), ("La", "Lanthanum", 138.91),
+    ("Ce", "Cerium", 140.12), ("Pr", "Praseodymium", 140.91), ("Nd", "Neodymium", 144.24),
+    ("Pm", "Promethium", 145.0), ("Sm", "Samarium", 150.36), ("Eu", "Europium", 151.96),
+    ("Gd", "Gadolinium", 157.25), ("Tb", "Terbium", 158.93), ("Dy", "Dysprosium", 162.50),
+    ("Ho", "Holmium", 164.93), ("Er", "Erbium", 167.26), ("Tm", "Thulium", 168.93),
+    ("Yb", "Ytterbium", 173.05), ("Lu", "Lutetium", 174.97), ("Hf", "Hafnium", 178.49),
+    ("Ta", "Tantalum", 180.95), ("W", "Tungsten", 183.84), ("Re", "Rhenium", 186.21),
+    ("Os", "Osmium", 190.23), ("Ir", "Iridium", 192.22), ("Pt", "Platinum", 195.08),
+    ("Au", "Gold", 196.97), ("Hg", "Mercury", 200.59), ("Tl", "Thallium", 204.38),
+    ("Pb", "Lead", 207.2), ("Bi", "Bismuth", 208.98), ("Po", "Polonium", 209.0),
+    ("At", "Astatine", 210.0), ("Rn", "Radon", 222.0), ("Fr", "Francium", 223.0),
+    ("Ra", "Radium", 226.0), ("Ac", "Actinium", 227.0), ("Th", "Thorium", 232.04),
+    ("Pa", "Protactinium", 231.04), ("U", "Uranium", 238.03), ("Np", "Neptunium", 237.0),
+    ("Pu", "Plutonium", 244.0), ("Am", "Americium", 243.0), ("Cm", "Curium", 247.0),
+    ("Bk", "Berkelium", 247.0), ("Cf", "Californium", 251.0), ("Es", "Einsteinium", 252.0),
+    ("Fm", "Fermium", 257.0), ("Md", "Mendelevium", 258.0), ("No", "Nobelium", 259.0),
+    ("Lr", "Lawrencium", 266.0), ("Rf", "Rutherfordium", 267.0), ("Db", "Dubnium", 268.0),
+    ("Sg", "Seaborgium", 269.0), ("Bh", "Bohrium", 270.0), ("Hs", "Hassium", 277.0),
+    ("Mt", "Meitnerium", 278.0), ("Ds", "Darmstadtium", 281.0), ("Rg", "Roentgenium", 282.0),
+    ("Cn", "Copernicium", 285.0), ("Nh", "Nihonium", 286.0), ("Fl", "Flerovium", 289.0),
+    ("Mc", "Moscovium", 290.0), ("Lv", "Livermorium", 293.0), ("Ts", "Tennessine", 294.0),
+    ("Og", "Oganesson", 294.0),
+];
+
+fn element(symbol: &str) -> Option<&'static (&'static str, &'static str, f64)> {
+    ELEMENTS.iter().find(|e| e.0 == symbol)
+}
+
+/// Atom counts of a formula in order of first appearance, `CuSO4*5H2O` gives Cu 1, S 1, O 9, H 10
+pub fn parse_formula(formula: &str) -> Result<Vec<(&'static str, u64)>, errors::Error> {
+    lazy_static! {
+        static ref STATE_RE: Regex = Regex::new(r"\((s|l|g|aq)\)$").unwrap();
+        static ref CHARGE_RE: Regex = Regex::new(r"(\^.*|[+-]+)$").unwrap();
+        static ref COEFF_RE: Regex = Regex::new(r"^(?P<n>\d+)").unwrap();
+    }
+
+    let formula = STATE_RE.replace(formula.trim(), "");
+    let formula = CHARGE_RE.replace(&formula, "");
+
+    let mut counts: Vec<(&'static str, u64)> = vec![];
+
+    // Hydrates and adducts like CuSO4·5H2O
+    for part in formula.split(&['.', '·', '*'][..]) {
+        let (coeff, part) = match COEFF_RE.captures(part) {
+            Some(c) => {
+                let n = c.name("n").unwrap();
+                (n.as_str().parse::<u64>().map_err(|_| too_big())?, &part[n.end()..])
+            },
+            None => (1, part),
+        };
+
+        let mut parser = FormulaParser {
+            chars: part.chars().collect(),
+            pos: 0,
+        };
+        for (e, n) in parser.group(None)? {
+            add_count(&mut counts, e, n.checked_mul(coeff).ok_or_else(too_big)?)?;
+        }
+        if parser.pos < parser.chars.len() {
+            return Err(errors::Error::ChemError(format!("Unexpected `{}` in `{}`", parser.chars[parser.pos], part)))
+        }
+    }
+
+    if counts.is_empty() {
+        return Err(errors::Error::ChemError(format!("`{}` has no elements in it", formula)))
+    }
+
+    Ok(counts)
+}
+
+fn add_count(counts: &mut Vec<(&'static str, u64)>, e: &'static str, n: u64) -> Result<(), errors::Error> {
+    match counts.iter_mut().find(|(s, _)| *s == e) {
+        Some(c) => c.1 = c.1.checked_add(n).ok_or_else(too_big)?,
+        None => counts.push((e, n)),
+    }
+    Ok(())
+}
+
+fn too_big() -> errors::Error {
+    errors::Error::ChemError(String::from("The numbers in this are too big to work with"))
+}
+
+struct FormulaParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl FormulaParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn count(&mut self) -> Result<u64, errors::Error> {
+        let mut n = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                n.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if n.is_empty() {
+            return Ok(1)
+        }
+        n.parse().map_err(|_| too_big())
+    }
+
+    fn group(&mut self, close: Option<char>) -> Result<Vec<(&'static str, u64)>, errors::Error> {
+        let mut counts = vec![];
+
+        while let Some(c) = self.peek() {
+            match c {
+                'A'..='Z' => {
+                    let mut symbol = c.to_string();
+                    self.pos += 1;
+                    if let Some(l) = self.peek().filter(|l| l.is_ascii_lowercase()) {
+                        symbol.push(l);
+                        self.pos += 1;
+                    }
+                    let e = match element(&symbol) {
+                        Some(e) => e.0,
+                        None => {return Err(errors::Error::ChemError(format!("Unknown element `{}`", symbol)))}
+                    };
+                    let n = self.count()?;
+                    add_count(&mut counts, e, n)?;
+                },
+                '(' | '[' | '{' => {
+                    self.pos += 1;
+                    let inner = self.group(Some(match c { '(' => ')', '[' => ']', _ => '}' }))?;
+                    let n = self.count()?;
+                    for (e, m) in inner {
+                        add_count(&mut counts, e, m.checked_mul(n).ok_or_else(too_big)?)?;
+                    }
+                },
+                c if Some(c) == close => {
+                    self.pos += 1;
+                    return Ok(counts)
+                },
+                _ => break,
+            }
+        }
+
+        match close {
+            Some(c) => Err(errors::Error::ChemError(format!("Missing `{}`", c))),
+            None => Ok(counts),
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Smallest whole number coefficients for a reaction like `H2 + O2 -> H2O`, as an mhchem equation
+pub fn balance_equation(equation: &str) -> Result<String, errors::Error> {
+    lazy_static! {
+        static ref ARROW_RE: Regex = Regex::new(r"\s*(<=>|<->|->|→|⇌|=)\s*").unwrap();
+        static ref PLUS_RE: Regex = Regex::new(r"\s+\+\s+").unwrap();
+        static ref COEFF_RE: Regex = Regex::new(r"^\d+\s*").unwrap();
+    }
+
+    let arrow = match ARROW_RE.captures(equation) {
+        Some(c) if matches!(&c[1], "<=>" | "⇌") => "<=>",
+        Some(_) => "->",
+        None => {return Err(errors::Error::ChemError(String::from("Expected a reaction like `H2 + O2 -> H2O`")))}
+    };
+
+    let sides: Vec<&str> = ARROW_RE.split(equation.trim()).collect();
+    if sides.len() != 2 {
+        return Err(errors::Error::ChemError(String::from("Expected exactly one arrow in the reaction")))
+    }
+
+    let species: Vec<Vec<String>> = sides.iter()
+        .map(|s| PLUS_RE.split(s.trim()).map(|sp| COEFF_RE.replace(sp.trim(), "").to_string()).collect())
+        .collect();
+    let n_reactants = species[0].len();
+    let species: Vec<String> = species.concat();
+
+    if species.iter().any(|s| s.is_empty()) {
+        return Err(errors::Error::ChemError(String::from("Empty species in the reaction")))
+    }
+
+    // One row per element, one column per species, products counted negative
+    let mut elements: Vec<&'static str> = vec![];
+    let mut columns = vec![];
+    for s in &species {
+        let counts = parse_formula(s)?;
+        for (e, _) in &counts {
+            if !elements.contains(e) {
+                elements.push(e);
+            }
+        }
+        columns.push(counts);
+    }
+
+    let mut matrix: Vec<Vec<i128>> = elements.iter().map(|e| {
+        columns.iter().enumerate().map(|(j, counts)| {
+            let n = counts.iter().find(|(s, _)| s == e).map_or(0, |(_, n)| *n as i128);
+            if j < n_reactants { n } else { -n }
+        }).collect()
+    }).collect();
+
+    // Fraction-free Gauss-Jordan elimination
+    let cols = species.len();
+    let mut pivots: Vec<usize> = vec![];
+    let mut row = 0;
+    for col in 0..cols {
+        let p = match (row..matrix.len()).find(|r| matrix[*r][col] != 0) {
+            Some(p) => p,
+            None => continue,
+        };
+        matrix.swap(row, p);
+
+        for r in 0..matrix.len() {
+            if r == row || matrix[r][col] == 0 {
+                continue;
+            }
+            let (a, b) = (matrix[row][col], matrix[r][col]);
+            let pivot_row = matrix[row].clone();
+            for (x, p) in matrix[r].iter_mut().zip(&pivot_row) {
+                *x = x.checked_mul(a)
+                    .zip(p.checked_mul(b))
+                    .and_then(|(xa, pb)| xa.checked_sub(pb))
+                    .ok_or_else(too_big)?;
+            }
+            let g = matrix[r].iter().fold(0, |g, x| gcd(g, *x));
+            if g > 1 {
+                matrix[r].iter_mut().for_each(|x| *x /= g);
+            }
+        }
+
+        pivots.push(col);
+        row += 1;
+        if row == matrix.len() {
+            break;
+        }
+    }
+
+    let free: Vec<usize> = (0..cols).filter(|c| !pivots.contains(c)).collect();
+    let free = match free.as_slice() {
+        [f] => *f,
+        [] => {return Err(errors::Error::ChemError(String::from("This reaction can't be balanced")))},
+        _ => {return Err(errors::Error::ChemError(String::from("This reaction has more than one independent way to balance it")))},
+    };
+
+    // Each pivot row reads a*x_p + b*x_free = 0
+    let lcm = pivots.iter().enumerate().try_fold(1, |l: i128, (r, p)| {
+        let a = matrix[r][*p].abs();
+        (l / gcd(l, a)).checked_mul(a).ok_or_else(too_big)
+    })?;
+    let mut coeffs = vec![0; cols];
+    coeffs[free] = lcm;
+    for (r, p) in pivots.iter().enumerate() {
+        coeffs[*p] = -matrix[r][free].checked_mul(lcm).ok_or_else(too_big)? / matrix[r][*p];
+    }
+
+    let g = coeffs.iter().fold(0, |g, x| gcd(g, *x));
+    let sign = if coeffs[0] < 0 { -1 } else { 1 };
+    let coeffs: Vec<i128> = coeffs.iter().map(|c| c * sign / g).collect();
+
+    if coeffs.iter().any(|c| *c <= 0) {
+        return Err(errors::Error::ChemError(String::from("This reaction can't be balanced")))
+    }
+
+    let term = |(c, s): (&i128, &String)| if *c == 1 { s.clone() } else { format!("{} {}", c, s) };
+    let lhs: Vec<String> = coeffs[..n_reactants].iter().zip(&species[..n_reactants]).map(term).collect();
+    let rhs: Vec<String> = coeffs[n_reactants..].iter().zip(&species[n_reactants..]).map(term).collect();
+
+    Ok(format!("{} {} {}", lhs.join(" + "), arrow, rhs.join(" + ")))
+}
+
+/// Molar mass and mass composition of a formula, as a LaTeX table
+pub fn molar_table(formula: &str) -> Result<String, errors::Error> {
+    let counts = parse_formula(formula)?;
+    let total: f64 = counts.iter().map(|(e, n)| element(e).unwrap().2 * *n as f64).sum();
+
+    let rows: Vec<String> = counts.iter().map(|(e, n)| {
+        let (symbol, name, mass) = element(e).unwrap();
+        let part = mass * *n as f64;
+        format!("{} ({}) & {} & {:.3} & {:.2}\\,\\% \\\\", name, symbol, n, part, part / total * 100.0)
+    }).collect();
+
+    Ok(format!(
+        "\\begin{{tabular}}{{lrrr}} \\multicolumn{{4}}{{l}}{{\\ce{{{}}} \\quad $M = {:.3}$ g/mol}} \\\\[2pt] \\hline Element & Atoms & Mass (g/mol) & Mass \\% \\\\ \\hline {} \\end{{tabular}}",
+        formula.trim().replace('·', "*"), total, rows.join(" ")
+    ))
+}
+
+async fn run(ctx: &Context, msg: &Message, cmd: &str, input: &str) -> Result<(), errors::Error> {
+    let latex = match cmd {
+        "ce" => Ok(format!("\\ce{{{}}}", input)),
+        "molar" => molar_table(input),
+        _ => balance_equation(input).map(|eq| format!("\\ce{{{}}}", eq)),
+    };
+
+    match latex {
+        Ok(l) => {
+            render_generated(ctx, msg, l).await?;
+        },
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+        },
+    }
+
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref CHEM_RE: Regex = Regex::new(format!(r"(?s)^{}(?P<cmd>ce|molar|balance) (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    }
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = CHEM_RE.captures(&inp_message.content) {
+        run(&ctx, &inp_message, &c["cmd"], &c["args"]).await.ok();
+    }
+}
+
+fn arg_or_err(arg: &Args) -> Result<&str, errors::Error> {
+    match arg.remains() {
+        Some(r) => Ok(r),
+        None => Err(errors::Error::ArgError(1, 0)),
+    }
+}
+
+#[command]
+#[description = "Render a chemical formula or reaction with mhchem"]
+#[usage = "<formula or reaction>"]
+#[example = "2H2 + O2 -> 2H2O"]
+pub async fn ce(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg_or_err(&arg) {
+        Ok(i) => i,
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+            return Err(e.into())
+        }
+    };
+
+    run(ctx, msg, "ce", input).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Molar mass and element composition of a formula"]
+#[usage = "<formula>"]
+#[example = "CuSO4*5H2O"]
+pub async fn molar(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg_or_err(&arg) {
+        Ok(i) => i,
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+            return Err(e.into())
+        }
+    };
+
+    run(ctx, msg, "molar", input).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Balance a chemical reaction"]
+#[usage = "<reactants> -> <products>"]
+#[example = "Fe + O2 -> Fe2O3"]
+pub async fn balance(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg_or_err(&arg) {
+        Ok(i) => i,
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+            return Err(e.into())
+        }
+    };
+
+    run(ctx, msg, "balance", input).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balanced(equation: &str) -> String {
+        balance_equation(equation).unwrap()
+    }
+
+    fn balance_err(equation: &str) -> String {
+        match balance_equation(equation) {
+            Err(errors::Error::ChemError(e)) => e,
+            r => panic!("Expected a chemistry error for `{}`, got {:?}", equation, r),
+        }
+    }
+
+    #[test]
+    fn simple_formulas() {
+        assert_eq!(parse_formula("H2O").unwrap(), vec![("H", 2), ("O", 1)]);
+        assert_eq!(parse_formula("NaCl").unwrap(), vec![("Na", 1), ("Cl", 1)]);
+        assert_eq!(parse_formula("C6H12O6").unwrap(), vec![("C", 6), ("H", 12), ("O", 6)]);
+    }
+
+    #[test]
+    fn parenthesised_formulas() {
+        assert_eq!(parse_formula("Ca(OH)2").unwrap(), vec![("Ca", 1), ("O", 2), ("H", 2)]);
+        assert_eq!(parse_formula("Mg3(PO4)2").unwrap(), vec![("Mg", 3), ("P", 2), ("O", 8)]);
+        assert_eq!(parse_formula("K4[Fe(CN)6]").unwrap(), vec![("K", 4), ("Fe", 1), ("C", 6), ("N", 6)]);
+        assert_eq!(parse_formula("{[(CH3)2]3}2").unwrap(), vec![("C", 12), ("H", 36)]);
+    }
+
+    #[test]
+    fn hydrates() {
+        let copper_sulfate = vec![("Cu", 1), ("S", 1), ("O", 9), ("H", 10)];
+        assert_eq!(parse_formula("CuSO4*5H2O").unwrap(), copper_sulfate);
+        assert_eq!(parse_formula("CuSO4·5H2O").unwrap(), copper_sulfate);
+        assert_eq!(parse_formula("CuSO4.5H2O").unwrap(), copper_sulfate);
+        assert_eq!(parse_formula("Na2CO3.10H2O").unwrap(), vec![("Na", 2), ("C", 1), ("O", 13), ("H", 20)]);
+    }
+
+    #[test]
+    fn states_and_charges() {
+        assert_eq!(parse_formula("NaCl(aq)").unwrap(), vec![("Na", 1), ("Cl", 1)]);
+        assert_eq!(parse_formula("SO4^2-").unwrap(), vec![("S", 1), ("O", 4)]);
+        assert_eq!(parse_formula("NH4+").unwrap(), vec![("N", 1), ("H", 4)]);
+    }
+
+    #[test]
+    fn bad_formulas() {
+        assert!(parse_formula("Xy2").is_err());
+        assert!(parse_formula("Ca(OH2").is_err());
+        assert!(parse_formula("H2O)").is_err());
+        assert!(parse_formula("").is_err());
+    }
+
+    #[test]
+    fn huge_formulas() {
+        let err = |f: &str| matches!(parse_formula(f), Err(errors::Error::ChemError(_)));
+        assert!(err("H99999999999999999999999"));
+        assert!(err("99999999999999999999999H2O"));
+        assert!(err("(H4294967296)4294967296"));
+        assert!(err("H18446744073709551615H"));
+        assert!(err("4294967296H4294967296"));
+    }
+
+    #[test]
+    fn huge_equations() {
+        let err = |eq: &str| matches!(balance_equation(eq), Err(errors::Error::ChemError(_)));
+        assert!(err("C9223372036854775807H3 + O9223372036854775806 -> CO2 + H2O"));
+        assert!(err("C18446744073709551615H18446744073709551614 + O18446744073709551613 -> C18446744073709551612O18446744073709551611 + H18446744073709551610O18446744073709551609"));
+    }
+
+    #[test]
+    fn known_equations() {
+        assert_eq!(balanced("H2 + O2 -> H2O"), "2 H2 + O2 -> 2 H2O");
+        assert_eq!(balanced("C3H8 + O2 -> CO2 + H2O"), "C3H8 + 5 O2 -> 3 CO2 + 4 H2O");
+        assert_eq!(balanced("Fe + O2 = Fe2O3"), "4 Fe + 3 O2 -> 2 Fe2O3");
+        assert_eq!(balanced("KMnO4 + HCl → KCl + MnCl2 + H2O + Cl2"), "2 KMnO4 + 16 HCl -> 2 KCl + 2 MnCl2 + 8 H2O + 5 Cl2");
+        assert_eq!(balanced("Ca(OH)2 + H3PO4 -> Ca3(PO4)2 + H2O"), "3 Ca(OH)2 + 2 H3PO4 -> Ca3(PO4)2 + 6 H2O");
+        assert_eq!(balanced("CuSO4*5H2O -> CuSO4 + H2O"), "CuSO4*5H2O -> CuSO4 + 5 H2O");
+    }
+
+    #[test]
+    fn keeps_equilibrium_arrows() {
+        assert_eq!(balanced("N2 + H2 <=> NH3"), "N2 + 3 H2 <=> 2 NH3");
+        assert_eq!(balanced("N2 + H2 ⇌ NH3"), "N2 + 3 H2 <=> 2 NH3");
+    }
+
+    #[test]
+    fn ignores_given_coefficients() {
+        assert_eq!(balanced("3 H2 + 7 O2 -> H2O"), "2 H2 + O2 -> 2 H2O");
+    }
+
+    #[test]
+    fn impossible_reactions() {
+        assert_eq!(balance_err("H2 -> O2"), "This reaction can't be balanced");
+        assert_eq!(balance_err("NaCl -> Na + Cl2 + H2O"), "This reaction can't be balanced");
+    }
+
+    #[test]
+    fn underdetermined_reactions() {
+        assert_eq!(balance_err("H2 + O2 -> H2O + H2O2"), "This reaction has more than one independent way to balance it");
+    }
+
+    #[test]
+    fn malformed_reactions() {
+        assert!(balance_equation("H2 + O2").is_err());
+        assert!(balance_equation("H2 -> O2 -> H2O").is_err());
+        assert!(balance_equation("H2 +  -> H2O").is_err());
+    }
+}
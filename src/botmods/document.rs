@@ -0,0 +1,510 @@
+use regex::Regex;
+use serenity::{
+    async_trait,
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    builder::{
+        CreateAttachment,
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+        CreateMessage,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+        application::Interaction,
+        id::{
+            ChannelId,
+            MessageId,
+        },
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use tiny_skia::Color;
+use crate::{
+    PREFIX,
+    Interactables,
+    Editables,
+    botmods::{
+        errors,
+        errors::err_msg,
+        utils::{
+            loading_msg,
+            output_target,
+            OutputLevel,
+            Buttons,
+            BotModule,
+            Editable,
+            Interactable,
+            push_to_editables,
+            push_to_interactables,
+        },
+    },
+    render::{
+        compile_document,
+        raster_pixmap,
+        MAX_PAGES,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_DOCUMENT: BotModule = BotModule {
+        command_group: &DOCUMENT_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}doc(\s.*)?$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Full LaTeX documents"]
+#[commands(doc)]
+struct Document;
+
+/// Biggest source accepted, pasted or attached
+const MAX_DOC_BYTES: usize = 100_000;
+/// Page previews are letter sized, so they don't need the snippet scale
+const PAGE_SCALE: u32 = 2;
+
+const DEFAULT_PREAMBLE: &str = r"\documentclass{article}
+\usepackage{amsmath}
+\usepackage{amssymb}
+\usepackage{amsthm}
+\begin{document}
+";
+
+/// Removes a surrounding code block along with a language tag like ```latex
+fn strip_code_block(s: &str) -> &str {
+    let s = s.trim();
+    match s.strip_prefix("```").and_then(|s| s.strip_suffix("```")) {
+        Some(inner) => match inner.find('\n') {
+            Some(n) if !inner[..n].contains('\\') => &inner[n+1..],
+            _ => inner,
+        },
+        None => s,
+    }
+}
+
+/// The document from the message or its `.tex` attachment, wrapped in an article if it has no preamble
+async fn document_source(msg: &Message, args: &str) -> Result<String, errors::Error> {
+    let source = if !args.trim().is_empty() {
+        String::from(strip_code_block(args))
+    } else {
+        let attachment = match msg.attachments.iter().find(|a| a.filename.ends_with(".tex")) {
+            Some(a) => a,
+            None => {return Err(errors::Error::DocError(String::from("Give the document in a code block or attach a `.tex` file")))}
+        };
+        if attachment.size as usize > MAX_DOC_BYTES {
+            return Err(errors::Error::DocError(format!("`{}` is too big, the limit is {}KB", attachment.filename, MAX_DOC_BYTES / 1000)))
+        }
+        match String::from_utf8(attachment.download().await?) {
+            Ok(s) => s,
+            Err(_) => {return Err(errors::Error::DocError(format!("`{}` isn't UTF-8 text", attachment.filename)))}
+        }
+    };
+
+    if source.len() > MAX_DOC_BYTES {
+        return Err(errors::Error::DocError(format!("That's too long, the limit is {}KB", MAX_DOC_BYTES / 1000)))
+    }
+
+    if source.contains(r"\documentclass") {
+        Ok(source)
+    } else {
+        Ok(format!("{}{}\n\\end{{document}}\n", DEFAULT_PREAMBLE, source))
+    }
+}
+
+#[derive(Clone)]
+pub struct DocPreview {
+    /// An SVG of each page
+    pages: Vec<Vec<u8>>,
+    /// Rendered previews, by page number minus one
+    previews: Vec<Option<Vec<u8>>>,
+    page: usize,
+    inp_message: Message,
+    pdf_message: Option<Message>,
+    message: Option<Message>,
+}
+
+impl DocPreview {
+    pub fn new(inp_message: &Message) -> DocPreview {
+        DocPreview {
+            pages: vec![],
+            previews: vec![],
+            page: 1,
+            inp_message: inp_message.clone(),
+            pdf_message: None,
+            message: None,
+        }
+    }
+
+    fn preview(&mut self) -> Result<Vec<u8>, errors::Error> {
+        if let Some(p) = &self.previews[self.page - 1] {
+            return Ok(p.clone())
+        }
+
+        let png = raster_pixmap(&self.pages[self.page - 1], PAGE_SCALE, Color::WHITE)?.encode_png()?;
+
+        self.previews[self.page - 1] = Some(png.clone());
+        Ok(png)
+    }
+
+    /// Compiles the document and posts the PDF with a preview, in place of the `old` messages
+    async fn compile(&mut self, ctx: &Context, source: &str, old: &[Message]) -> Result<(), errors::Error> {
+        let (c_id, level) = output_target(ctx, &self.inp_message.channel_id, &self.inp_message.author).await?;
+
+        let doc = if level >= OutputLevel::File {
+            compile_document(source).await.map_err(errors::Error::from)
+        } else {
+            Err(errors::Error::DocError(String::from("Documents need permission to attach files")))
+        };
+
+        let (first, rest) = match old.split_first() {
+            Some((f, r)) => (Some(f), r),
+            None => (None, &[][..]),
+        };
+        for m in rest {
+            m.delete(&ctx.http).await?;
+        }
+
+        let doc = match doc {
+            Ok(d) => d,
+            Err(e) => {
+                self.message = Some(err_msg(ctx, &c_id, first, Some(&self.inp_message.author), &e).await?);
+                return Ok(())
+            },
+        };
+
+        if let Some(m) = first {
+            m.delete(&ctx.http).await?;
+        }
+
+        let content = if doc.pages.len() >= MAX_PAGES {
+            format!("Previews stop at page {}, the PDF has the rest", MAX_PAGES)
+        } else {
+            String::new()
+        };
+
+        let pdf = doc.pdf;
+        let mut m = CreateMessage::new().add_file(CreateAttachment::bytes(pdf, "document.pdf"));
+        if !content.is_empty() {
+            m = m.content(&content);
+        }
+        self.pdf_message = Some(c_id.send_message(&ctx.http, m).await?);
+
+        self.previews = vec![None; doc.pages.len()];
+        self.pages = doc.pages;
+        self.page = 1;
+
+        if !self.pages.is_empty() {
+            self.send(ctx, c_id, level, None).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the preview of the current page, in place of `old` if given
+    async fn send(&mut self, ctx: &Context, c_id: ChannelId, level: OutputLevel, old: Option<&Message>) -> Result<(), errors::Error> {
+        let image = match self.preview() {
+            Ok(i) => i,
+            Err(e) => {
+                self.message = Some(err_msg(ctx, &c_id, old, Some(&self.inp_message.author), &e).await?);
+                return Ok(())
+            },
+        };
+
+        if let Some(m) = old {
+            m.delete(&ctx.http).await?;
+        }
+
+        let mut buttons = vec![Buttons::Delete];
+        if self.pages.len() > 1 {
+            buttons.extend(vec![Buttons::Prev, Buttons::Next]);
+        }
+
+        let title = format!("Page {} of {}", self.page, self.pages.len());
+        let author = &self.inp_message.author;
+
+        let m = if level == OutputLevel::Embed {
+            CreateMessage::new().embed(
+                CreateEmbed::new()
+                    .title(&title)
+                    .image("attachment://page.png")
+                    .footer(CreateEmbedFooter::new(format!("Requested by {}", author.tag())).icon_url(author.face()))
+            )
+        } else {
+            CreateMessage::new().content(format!("**{}**", title))
+        };
+        self.message = Some(c_id.send_message(&ctx.http, m
+            .add_file(CreateAttachment::bytes(image, "page.png"))
+            .components(Buttons::add_buttons(buttons))
+        ).await?);
+
+        Ok(())
+    }
+
+    fn messages(&self) -> Vec<Message> {
+        self.message.iter().chain(self.pdf_message.iter()).cloned().collect()
+    }
+}
+
+#[async_trait]
+impl Editable for DocPreview {
+    async fn edit(&mut self, ctx: &Context) -> Result<(), errors::Error> {
+        lazy_static! {
+            static ref DOC_RE: Regex = Regex::new(format!(r"(?s)^{}doc(?P<args>\s.*)?$", PREFIX.as_str()).as_str()).unwrap();
+        };
+
+        let old_ms = self.messages();
+        let inp_message = self.inp_message.channel_id.message(&ctx, self.inp_message.id).await?;
+
+        let input = match DOC_RE.captures(&inp_message.content) {
+            Some(c) => String::from(c.name("args").map_or("", |a| a.as_str())),
+            None => {return Ok(())}
+        };
+
+        *self = DocPreview::new(&inp_message);
+        match document_source(&inp_message, &input).await {
+            Ok(s) => self.compile(ctx, &s, &old_ms).await?,
+            Err(e) => {
+                for m in old_ms.iter().skip(1) {
+                    m.delete(&ctx.http).await?;
+                }
+                self.message = Some(err_msg(ctx, &inp_message.channel_id, old_ms.first(), Some(&inp_message.author), &e).await?);
+            },
+        }
+
+        let interactables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Interactables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut interactables = interactables_lock.write().await;
+            interactables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            'outer: for (p, i) in interactables.iter().enumerate() {
+                for j in i.get_response_message_id() {
+                    if old_ms.iter().any(|m| m.id == j) {
+                        pos = Some(p);
+                        break 'outer;
+                    }
+                }
+            }
+
+            if let Some(p) = pos {
+                interactables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        self.messages().iter().map(|m| m.id).collect()
+    }
+
+    fn get_response_messages(&self) -> Vec<(ChannelId, MessageId)> {
+        self.messages().iter().map(|m| (m.channel_id, m.id)).collect()
+    }
+
+    fn get_input_message_id(&self) -> MessageId {
+        self.inp_message.id.clone()
+    }
+
+    fn get_command_pattern(&self) -> Regex {
+        MOD_DOCUMENT.command_pattern[0].clone()
+    }
+}
+
+#[async_trait]
+impl Interactable for DocPreview {
+    async fn interaction_respond(&mut self, ctx: &Context, interaction: Interaction) -> Result<(), errors::Error> {
+        let component_interaction = match interaction {
+            Interaction::Component(m) => m,
+            _ => {return Ok(())}
+        };
+
+        let button = Buttons::from(component_interaction.data.custom_id.as_str());
+
+        if button.author_only() && self.inp_message.author != component_interaction.user {
+            component_interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the person who posted the document can do that")
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
+        }
+
+        component_interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let old_m = match self.message.clone() {
+            Some(m) => m,
+            None => {return Ok(())}
+        };
+
+        match button {
+            Buttons::Delete => {
+                for m in self.messages() {
+                    m.delete(&ctx).await?;
+                }
+                self.message = None;
+                self.pdf_message = None;
+            },
+            Buttons::Next | Buttons::Prev => {
+                let page = match button {
+                    Buttons::Next => usize::min(self.page + 1, self.pages.len()),
+                    _ => usize::max(self.page - 1, 1),
+                };
+
+                // Attachments can't be edited, so the preview is sent again
+                if page != self.page {
+                    self.page = page;
+                    let (c_id, level) = output_target(ctx, &self.inp_message.channel_id, &self.inp_message.author).await?;
+                    self.send(ctx, c_id, level, Some(&old_m)).await?;
+                }
+            },
+            _ => {}
+        }
+
+        let editables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut editables = editables_lock.write().await;
+            editables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            for (p, i) in editables.iter().enumerate() {
+                if self.inp_message.id == i.get_input_message_id() {
+                    pos = Some(p);
+                    break;
+                }
+            }
+
+            if let Some(p) = pos {
+                editables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        self.messages().iter().map(|m| m.id).collect()
+    }
+}
+
+async fn run(ctx: &Context, msg: &Message, args: &str, old: Option<&Message>) -> Result<(), errors::Error> {
+    match document_source(msg, args).await {
+        Ok(s) => {
+            let mut d = DocPreview::new(msg);
+            let old: Vec<Message> = old.into_iter().cloned().collect();
+            d.compile(ctx, &s, &old).await?;
+
+            push_to_interactables(ctx, Box::new(d.clone())).await;
+            push_to_editables(ctx, Box::new(d.clone())).await;
+        },
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, old, Some(&msg.author), &e).await?;
+        },
+    }
+
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref DOC_RE: Regex = Regex::new(format!(r"(?s)^{}doc(?P<args>\s.*)?$", PREFIX.as_str()).as_str()).unwrap();
+    };
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = DOC_RE.captures(&inp_message.content) {
+        run(&ctx, &inp_message, c.name("args").map_or("", |a| a.as_str()), None).await.ok();
+    }
+}
+
+#[command]
+#[description = "Compile a whole LaTeX document to a PDF, with a preview of each page. Paste it in a code block or attach a `.tex` file. Without a `\\documentclass` it goes in an `article` with the AMS packages"]
+#[usage = "<document or .tex attachment>"]
+#[example = "```latex\n\\section{Proof}\nBy induction on $n$\\dots\n```"]
+pub async fn doc(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let lm = loading_msg(ctx, &msg.channel_id, &msg.author).await?;
+    run(ctx, msg, args.rest(), Some(&lm)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn source(args: &str) -> Result<String, errors::Error> {
+        block_on(document_source(&Message::default(), args))
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(strip_code_block("```latex\n\\section{A}\n```"), "\\section{A}\n");
+        assert_eq!(strip_code_block("```\\section{A}```"), "\\section{A}");
+        assert_eq!(strip_code_block("  \\section{A}  "), "\\section{A}");
+    }
+
+    #[test]
+    fn bare_bodies_get_a_preamble() {
+        assert_eq!(
+            source("```tex\nHello $x^2$\n```").unwrap(),
+            format!("{}Hello $x^2$\n\n\\end{{document}}\n", DEFAULT_PREAMBLE)
+        );
+    }
+
+    #[test]
+    fn full_documents_stay_as_they_are() {
+        let doc = "\\documentclass{amsart}\n\\begin{document}\nHi\n\\end{document}";
+        assert_eq!(source(&format!("```latex\n{}\n```", doc)).unwrap(), format!("{}\n", doc));
+    }
+
+    #[test]
+    fn document_limits() {
+        assert!(source(&"a".repeat(MAX_DOC_BYTES)).is_ok());
+        assert!(matches!(source(&"a".repeat(MAX_DOC_BYTES + 1)), Err(errors::Error::DocError(_))));
+    }
+
+    #[test]
+    fn needs_a_document() {
+        match source("   ") {
+            Err(errors::Error::DocError(e)) => assert_eq!(e, "Give the document in a code block or attach a `.tex` file"),
+            r => panic!("Expected a document error, got {:?}", r),
+        }
+    }
+}
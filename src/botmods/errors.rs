@@ -13,15 +13,34 @@ use serenity::{
     id::ChannelId,
     user::User,
     },
+    builder::{
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateMessage,
+    },
     prelude::*,
 };
 use reqwest;
+use ron;
+use zip;
+use gif;
+use crate::render::RenderError;
+use crate::botmods::utils::{
+    OutputLevel,
+    output_level,
+    output_target,
+};
 
 pub async fn err_msg(ctx: &Context, c_id: &ChannelId, loading_msg: Option<&Message>, for_user: Option<&User>, err: &(impl StdErr + Display)) -> Result<Message, SerenityError> {
     if let Some(l) = loading_msg {
         l.delete(&ctx.http).await?;
     }
 
+    let (c_id, level) = match for_user {
+        Some(u) => output_target(ctx, c_id, u).await?,
+        None => (*c_id, output_level(ctx, c_id).await),
+    };
+
     let mut err_str = format!("There was an error:\n{}", err).to_string();
 
     if err_str.len() > 2000 {
@@ -29,25 +48,18 @@ pub async fn err_msg(ctx: &Context, c_id: &ChannelId, loading_msg: Option<&Messa
         err_str.push_str("...");
     };
 
-    c_id.send_message(&ctx.http, |m|{
-        m.embed(|e| {
-            e.title("Error");
-            e.description(err_str);
-            if let Some(u) = for_user {
-                e.footer(|f| {
-                    if let Some(a) = u.avatar_url() {
-                        f.icon_url(a);
-                    } else {
-                        f.icon_url(u.default_avatar_url());
-                    }
-                    f.text(format!("Requested by {}#{}", u.name, u.discriminator));
-                    f
-                });
-            }
-            e
-        });
-        m
-    }).await
+    if level < OutputLevel::Embed {
+        return c_id.say(&ctx.http, err_str).await
+    }
+
+    let mut e = CreateEmbed::new()
+        .title("Error")
+        .description(err_str);
+    if let Some(u) = for_user {
+        e = e.footer(CreateEmbedFooter::new(format!("Requested by {}", u.tag())).icon_url(u.face()));
+    }
+
+    c_id.send_message(&ctx.http, CreateMessage::new().embed(e)).await
 }
 
 #[derive(Debug)]
@@ -57,9 +69,21 @@ pub enum Error {
     IOError(io::Error),
     ArgError(u8, u8),
     MathError(String),
+    LintError(String),
     RequestError(reqwest::Error),
     WolfError(String, u32),
-    SerenityError(serenity::Error),
+    SerenityError(Box<serenity::Error>),
+    RonError(ron::Error),
+    SettingError(String),
+    ChemError(String),
+    SymbolError(String),
+    SnippetError(String),
+    ZipError(zip::result::ZipError),
+    TableError(String),
+    LogicError(String),
+    StepsError(String),
+    DocError(String),
+    GifError(gif::EncodingError),
     NoImgError()
 }
 
@@ -89,7 +113,38 @@ impl From<reqwest::Error> for Error {
 
 impl From<serenity::Error> for Error {
     fn from(e: serenity::Error) -> Error {
-        Error::SerenityError(e)
+        Error::SerenityError(Box::new(e))
+    }
+}
+
+impl From<ron::Error> for Error {
+    fn from(e: ron::Error) -> Error {
+        Error::RonError(e)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Error {
+        Error::ZipError(e)
+    }
+}
+
+impl From<gif::EncodingError> for Error {
+    fn from(e: gif::EncodingError) -> Error {
+        Error::GifError(e)
+    }
+}
+
+impl From<RenderError> for Error {
+    fn from(e: RenderError) -> Error {
+        match e {
+            RenderError::SVGError(e) => Error::SVGError(e),
+            RenderError::PNGError(e) => Error::PNGError(e),
+            RenderError::IOError(e) => Error::IOError(e),
+            RenderError::GifError(e) => Error::GifError(e),
+            RenderError::MathError(e) => Error::MathError(e),
+            RenderError::NoImgError() => Error::NoImgError(),
+        }
     }
 }
 
@@ -101,9 +156,21 @@ impl Display for Error {
             Error::IOError(e) => f.write_str(&format!("I/O error: {}", e)),
             Error::ArgError(rec, need) => f.write_str(&format!("Expected {} argument(s), recieved {}", need, rec)),
             Error::MathError(e) => f.write_str(&format!("Compilation error:\n```{}```", e)),
+            Error::LintError(e) => f.write_str(&format!("Syntax error:\n{}", e)),
             Error::RequestError(e) => f.write_str(&format!("Request error:\n{}", e)),
             Error::WolfError(e, c) => f.write_str(&format!("Wolfram error {} :\n{}", c, e)),
             Error::SerenityError(e) => f.write_str(&format!("Serenity Error:\n{}", e)),
+            Error::RonError(e) => f.write_str(&format!("Error writing settings:\n{}", e)),
+            Error::SettingError(e) => f.write_str(&format!("Settings error:\n{}", e)),
+            Error::ChemError(e) => f.write_str(&format!("Chemistry error:\n{}", e)),
+            Error::SymbolError(e) => f.write_str(&format!("Symbol lookup error:\n{}", e)),
+            Error::SnippetError(e) => f.write_str(&format!("Snippet error:\n{}", e)),
+            Error::ZipError(e) => f.write_str(&format!("Error making the zip: {}", e)),
+            Error::TableError(e) => f.write_str(&format!("Table error:\n{}", e)),
+            Error::LogicError(e) => f.write_str(&format!("Logic error:\n{}", e)),
+            Error::StepsError(e) => f.write_str(&format!("Steps error:\n{}", e)),
+            Error::DocError(e) => f.write_str(&format!("Document error:\n{}", e)),
+            Error::GifError(e) => f.write_str(&format!("Error making the GIF: {}", e)),
             Error::NoImgError() => f.write_str(&format!("Error:\nNo image"))
         }
     }
@@ -115,7 +182,10 @@ impl StdErr for Error {
             Error::SVGError(inner) => Some(inner),
             Error::PNGError(inner) => Some(inner),
             Error::IOError(inner) => Some(inner),
-            Error::SerenityError(inner) => Some(inner),
+            Error::SerenityError(inner) => Some(inner.as_ref()),
+            Error::RonError(inner) => Some(inner),
+            Error::ZipError(inner) => Some(inner),
+            Error::GifError(inner) => Some(inner),
             _ => None,
         }
     }
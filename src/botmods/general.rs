@@ -4,6 +4,11 @@ use serenity::{
         channel::Message,
         Permissions,
     },
+    builder::{
+        CreateEmbed,
+        CreateMessage,
+        EditMessage,
+    },
     prelude::*,
     framework::standard::
     {
@@ -13,7 +18,6 @@ use serenity::{
             group,
         },
     },
-    client::bridge::gateway::ShardId,
 };
 use std::time::Instant;
 use crate::{
@@ -27,9 +31,9 @@ lazy_static!(
     pub static ref MOD_GENERAL: BotModule = BotModule {
         command_group: &GENERAL_GROUP,
         command_pattern: vec![
-            Regex::new(format!(r"^{}ping$", PREFIX.as_str()).as_str()).unwrap(),
-            Regex::new(format!(r"^{}about$", PREFIX.as_str()).as_str()).unwrap(),
-            Regex::new(format!(r"^{}invite$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}ping$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}about$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}invite$", PREFIX.as_str()).as_str()).unwrap(),
         ],
         editors: vec![],
         interactors: vec![],
@@ -55,32 +59,25 @@ pub async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
     let shard_manager = match data.get::<ShardManagerContainer>() {
         Some(v) => v,
         None => {
-            msg.edit(ctx, |m| m.content("Error: Couldn't get shard manager")).await?;
+            msg.edit(ctx, EditMessage::new().content("Error: Couldn't get shard manager")).await?;
             return Ok(());
         },
     };
-    let manager = shard_manager.lock().await;
-    let runners = manager.runners.lock().await;
-    let runner = match runners.get(&ShardId(ctx.shard_id)) {
+    let runners = shard_manager.runners.lock().await;
+    let runner = match runners.get(&ctx.shard_id) {
         Some(runner) => runner,
         None => {
-            msg.edit(ctx, |m| m.content("Error: No shard found")).await?;
+            msg.edit(ctx, EditMessage::new().content("Error: No shard found")).await?;
             return Ok(());
         },
     }; 
 
     match runner.latency {
         Some(d) => {
-            msg.edit(&ctx, |m| {
-                m.content(format!("Ping:    {} ms\nAPI latency:    {} ms", t, d.as_millis()));
-                m
-            }).await?;
+            msg.edit(&ctx, EditMessage::new().content(format!("Ping:    {} ms\nAPI latency:    {} ms", t, d.as_millis()))).await?;
         }
         None => {
-            msg.edit(&ctx, |m| {
-                m.content(format!("Ping:    {} ms\nAPI latency:    Not available", t));
-                m
-            }).await?;
+            msg.edit(&ctx, EditMessage::new().content(format!("Ping:    {} ms\nAPI latency:    Not available", t))).await?;
         }
     };
     
@@ -90,35 +87,30 @@ pub async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
 #[command]
 #[description = "About the bot"]
 pub async fn about(ctx: &Context, msg: &Message) -> CommandResult {
-    let curr_user = ctx.cache.current_user().await;
+    let curr_user = ctx.cache.current_user().clone();
     
     let av = curr_user.face();
 
-    msg.channel_id.send_message(&ctx.http, |m| {
-                m.embed(|e| {
-                    e.title(format!("About {}", curr_user.name));
-                    e.description("A Discord bot to compile Latex/AsciiMath snippets ~~and fetch steps from Wolfram|Alpha~~, written in Rust");
-                    e.image(av);
-                    e.field("Made by", "Nanu#3294", false);
-                    e.field("Check out the github repo!", "[Nanu00/wabot-rs](https://github.com/Nanu00/wabot-rs)", false);
-                    e
-                });
-                m
-            }).await?;
+    msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(
+                CreateEmbed::new()
+                    .title(format!("About {}", curr_user.name))
+                    .description("A Discord bot to compile Latex/AsciiMath snippets ~~and fetch steps from Wolfram|Alpha~~, written in Rust")
+                    .image(av)
+                    .field("Made by", "Nanu#3294", false)
+                    .field("Check out the github repo!", "[Nanu00/wabot-rs](https://github.com/Nanu00/wabot-rs)", false)
+            )).await?;
     Ok(())
 }
 
 #[command]
 #[description = "Get an invite link!"]
 pub async fn invite(ctx: &Context, msg: &Message) -> CommandResult {
-    let curr_user = ctx.cache.current_user().await;
-    match curr_user.invite_url(&ctx.http, Permissions::from_bits(392256).unwrap()).await {
-        Ok(url) => {
-            msg.channel_id.say(&ctx.http, url).await?
-        },
-        Err(e) => {
-            msg.channel_id.say(&ctx.http, format!("Error generating invite: {}", e)).await?
-        },
-    };
+    let curr_id = ctx.cache.current_user().id;
+    let url = format!(
+        "https://discord.com/api/oauth2/authorize?client_id={}&scope=bot&permissions={}",
+        curr_id,
+        Permissions::from_bits(392256).unwrap().bits()
+    );
+    msg.channel_id.say(&ctx.http, url).await?;
     Ok(())
 }
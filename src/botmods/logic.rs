@@ -0,0 +1,492 @@
+use regex::Regex;
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use crate::{
+    PREFIX,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::{
+            render_generated,
+        },
+        utils::BotModule,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_LOGIC: BotModule = BotModule {
+        command_group: &LOGIC_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}truth .*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Logic commands"]
+#[commands(truth)]
+struct Logic;
+
+/// 64 rows is about as tall as a render can get and still be readable
+const MAX_VARS: usize = 6;
+/// Brackets, negations and arrows the parser will recurse through before giving up
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+enum Token {
+    Var(String),
+    Const(bool),
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Iff,
+    Open,
+    Close,
+}
+
+#[derive(Debug)]
+pub enum Prop {
+    Var(String),
+    Const(bool),
+    Not(Box<Prop>),
+    And(Box<Prop>, Box<Prop>),
+    Or(Box<Prop>, Box<Prop>),
+    Xor(Box<Prop>, Box<Prop>),
+    Implies(Box<Prop>, Box<Prop>),
+    Iff(Box<Prop>, Box<Prop>),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, errors::Error> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let rest: String = chars[i..].iter().take(3).collect();
+        let (token, len) = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            },
+            '(' | '[' => (Token::Open, 1),
+            ')' | ']' => (Token::Close, 1),
+            '~' | '!' | '¬' => (Token::Not, 1),
+            '∧' | '&' | '*' => (Token::And, if rest.starts_with("&&") { 2 } else { 1 }),
+            '∨' | '|' | '+' => (Token::Or, if rest.starts_with("||") { 2 } else { 1 }),
+            '^' | '⊕' => (Token::Xor, 1),
+            '→' | '⇒' => (Token::Implies, 1),
+            '↔' | '⇔' => (Token::Iff, 1),
+            '/' if rest.starts_with("/\\") => (Token::And, 2),
+            '\\' if rest.starts_with("\\/") => (Token::Or, 2),
+            '-' | '=' if rest.starts_with("->") || rest.starts_with("=>") => (Token::Implies, 2),
+            '<' if rest == "<->" || rest == "<=>" => (Token::Iff, 3),
+            '⊤' | '1' => (Token::Const(true), 1),
+            '⊥' | '0' => (Token::Const(false), 1),
+            c if c.is_alphabetic() => {
+                let word: String = chars[i..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').collect();
+                let len = word.chars().count();
+                let token = match word.to_lowercase().as_str() {
+                    "not" => Token::Not,
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "xor" => Token::Xor,
+                    "implies" => Token::Implies,
+                    "iff" => Token::Iff,
+                    "true" => Token::Const(true),
+                    "false" => Token::Const(false),
+                    _ => Token::Var(word),
+                };
+                (token, len)
+            },
+            c => {return Err(errors::Error::LogicError(format!("Unexpected `{}`", c)))},
+        };
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Var(v) => format!("`{}`", v),
+            Token::Const(b) => format!("`{}`", b),
+            Token::Not => String::from("`~`"),
+            Token::And => String::from("`&`"),
+            Token::Or => String::from("`|`"),
+            Token::Xor => String::from("`^`"),
+            Token::Implies => String::from("`->`"),
+            Token::Iff => String::from("`<->`"),
+            Token::Open => String::from("`(`"),
+            Token::Close => String::from("`)`"),
+        }
+    }
+}
+
+/// Precedence climbing, from `↔` binding loosest to `¬` binding tightest
+struct PropParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl PropParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Runs `f` one level deeper, so a formula can't nest deep enough to overflow the stack
+    fn nested(&mut self, f: fn(&mut PropParser) -> Result<Prop, errors::Error>) -> Result<Prop, errors::Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(errors::Error::LogicError(format!("That's nested too deep, the limit is {} levels", MAX_DEPTH)))
+        }
+        self.depth += 1;
+        let prop = f(self);
+        self.depth -= 1;
+        prop
+    }
+
+    fn iff(&mut self) -> Result<Prop, errors::Error> {
+        let mut left = self.implies()?;
+        while let Some(Token::Iff) = self.peek() {
+            self.pos += 1;
+            left = Prop::Iff(Box::new(left), Box::new(self.implies()?));
+        }
+        Ok(left)
+    }
+
+    // Right associative, so `p → q → r` is `p → (q → r)`
+    fn implies(&mut self) -> Result<Prop, errors::Error> {
+        let left = self.or()?;
+        if let Some(Token::Implies) = self.peek() {
+            self.pos += 1;
+            return Ok(Prop::Implies(Box::new(left), Box::new(self.nested(PropParser::implies)?)))
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Prop, errors::Error> {
+        let mut left = self.xor()?;
+        while let Some(Token::Or) = self.peek() {
+            self.pos += 1;
+            left = Prop::Or(Box::new(left), Box::new(self.xor()?));
+        }
+        Ok(left)
+    }
+
+    fn xor(&mut self) -> Result<Prop, errors::Error> {
+        let mut left = self.and()?;
+        while let Some(Token::Xor) = self.peek() {
+            self.pos += 1;
+            left = Prop::Xor(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Prop, errors::Error> {
+        let mut left = self.not()?;
+        while let Some(Token::And) = self.peek() {
+            self.pos += 1;
+            left = Prop::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Prop, errors::Error> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Not) => Ok(Prop::Not(Box::new(self.nested(PropParser::not)?))),
+            Some(Token::Var(v)) => Ok(Prop::Var(v)),
+            Some(Token::Const(b)) => Ok(Prop::Const(b)),
+            Some(Token::Open) => {
+                let inner = self.nested(PropParser::iff)?;
+                match self.peek() {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    },
+                    _ => Err(errors::Error::LogicError(String::from("Missing `)`"))),
+                }
+            },
+            Some(t) => Err(errors::Error::LogicError(format!("Expected a variable, found {}", t.describe()))),
+            None => Err(errors::Error::LogicError(String::from("The formula ends too early"))),
+        }
+    }
+}
+
+impl Prop {
+    pub fn parse(s: &str) -> Result<Prop, errors::Error> {
+        let mut parser = PropParser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            depth: 0,
+        };
+        let prop = parser.iff()?;
+        match parser.peek() {
+            Some(t) => Err(errors::Error::LogicError(format!("Unexpected {} after the formula", t.describe()))),
+            None => Ok(prop),
+        }
+    }
+
+    fn vars(&self, out: &mut Vec<String>) {
+        match self {
+            Prop::Var(v) => {
+                if !out.contains(v) {
+                    out.push(v.clone());
+                }
+            },
+            Prop::Const(_) => {},
+            Prop::Not(p) => p.vars(out),
+            Prop::And(a, b) | Prop::Or(a, b) | Prop::Xor(a, b) | Prop::Implies(a, b) | Prop::Iff(a, b) => {
+                a.vars(out);
+                b.vars(out);
+            },
+        }
+    }
+
+    fn eval(&self, vars: &[String], values: &[bool]) -> bool {
+        match self {
+            Prop::Var(v) => values[vars.iter().position(|x| x == v).unwrap()],
+            Prop::Const(b) => *b,
+            Prop::Not(p) => !p.eval(vars, values),
+            Prop::And(a, b) => a.eval(vars, values) && b.eval(vars, values),
+            Prop::Or(a, b) => a.eval(vars, values) || b.eval(vars, values),
+            Prop::Xor(a, b) => a.eval(vars, values) != b.eval(vars, values),
+            Prop::Implies(a, b) => !a.eval(vars, values) || b.eval(vars, values),
+            Prop::Iff(a, b) => a.eval(vars, values) == b.eval(vars, values),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Prop::Iff(..) => 0,
+            Prop::Implies(..) => 1,
+            Prop::Or(..) => 2,
+            Prop::Xor(..) => 3,
+            Prop::And(..) => 4,
+            Prop::Not(_) => 5,
+            Prop::Var(_) | Prop::Const(_) => 6,
+        }
+    }
+
+    /// The formula in LaTeX math, with only the brackets it needs
+    pub fn to_latex(&self) -> String {
+        let wrap = |p: &Prop, min: u8| {
+            if p.precedence() < min {
+                format!("({})", p.to_latex())
+            } else {
+                p.to_latex()
+            }
+        };
+
+        match self {
+            Prop::Var(v) if v.chars().count() > 1 => format!("\\mathit{{{}}}", v.replace('_', "\\_")),
+            Prop::Var(v) => v.clone(),
+            Prop::Const(true) => String::from("\\top"),
+            Prop::Const(false) => String::from("\\bot"),
+            Prop::Not(p) => format!("\\lnot {}", wrap(p, 5)),
+            Prop::And(a, b) => format!("{} \\land {}", wrap(a, 4), wrap(b, 5)),
+            Prop::Xor(a, b) => format!("{} \\oplus {}", wrap(a, 3), wrap(b, 4)),
+            Prop::Or(a, b) => format!("{} \\lor {}", wrap(a, 2), wrap(b, 3)),
+            Prop::Implies(a, b) => format!("{} \\to {}", wrap(a, 2), wrap(b, 1)),
+            Prop::Iff(a, b) => format!("{} \\leftrightarrow {}", wrap(a, 0), wrap(b, 1)),
+        }
+    }
+}
+
+/// The truth table as a LaTeX tabular, with whether the formula is a tautology, contradiction or neither
+pub fn truth_table(formula: &str) -> Result<String, errors::Error> {
+    let prop = Prop::parse(formula)?;
+
+    let mut vars = vec![];
+    prop.vars(&mut vars);
+    vars.sort();
+
+    if vars.len() > MAX_VARS {
+        return Err(errors::Error::LogicError(format!("That has {} variables, the limit is {}", vars.len(), MAX_VARS)))
+    }
+
+    // Rows go from all true down to all false, the way they're usually written
+    let n = vars.len();
+    let mut rows = vec![];
+    let mut true_rows = 0;
+    for i in 0..(1usize << n) {
+        let values: Vec<bool> = (0..n).map(|v| i & (1 << (n - 1 - v)) == 0).collect();
+        let result = prop.eval(&vars, &values);
+        if result {
+            true_rows += 1;
+        }
+
+        let cells: Vec<&str> = values.iter().chain(std::iter::once(&result)).map(|b| if *b { "T" } else { "F" }).collect();
+        rows.push(format!("{} \\\\", cells.join(" & ")));
+    }
+
+    let total = 1usize << n;
+    let verdict = if true_rows == total {
+        String::from("Tautology")
+    } else if true_rows == 0 {
+        String::from("Contradiction")
+    } else {
+        format!("Satisfiable, true in {} of {} rows", true_rows, total)
+    };
+
+    let header: Vec<String> = vars.iter().map(|v| format!("${}$", Prop::Var(v.clone()).to_latex()))
+        .chain(std::iter::once(format!("${}$", prop.to_latex())))
+        .collect();
+
+    Ok(format!(
+        "\\begin{{tabular}}{{{}|c}} {} \\\\ \\hline {} \\hline \\multicolumn{{{}}}{{l}}{{\\textit{{{}}}}} \\end{{tabular}}",
+        "c".repeat(n), header.join(" & "), rows.join(" "), n + 1, verdict
+    ))
+}
+
+async fn run(ctx: &Context, msg: &Message, input: &str) -> Result<(), errors::Error> {
+    match truth_table(input) {
+        Ok(l) => {
+            render_generated(ctx, msg, l).await?;
+        },
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+        },
+    }
+
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref TRUTH_RE: Regex = Regex::new(format!(r"(?s)^{}truth (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    }
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = TRUTH_RE.captures(&inp_message.content) {
+        run(&ctx, &inp_message, &c["args"]).await.ok();
+    }
+}
+
+#[command]
+#[description = "Truth table for a propositional formula, using `~ & | ^ -> <->` or `not and or xor implies iff`"]
+#[usage = "<formula>"]
+#[example = "(p -> q) & ~r"]
+pub async fn truth(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    run(ctx, msg, input).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latex(formula: &str) -> String {
+        Prop::parse(formula).unwrap().to_latex()
+    }
+
+    fn logic_err(formula: &str) -> String {
+        match truth_table(formula) {
+            Err(errors::Error::LogicError(e)) => e,
+            r => panic!("Expected a logic error for `{}`, got {:?}", formula, r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn operator_spellings() {
+        for f in ["p & q", "p && q", "p * q", "p ∧ q", r"p /\ q", "p and q", "P AND q"] {
+            assert_eq!(latex(f).replace('P', "p"), r"p \land q", "{}", f);
+        }
+        assert_eq!(latex("~p | !q + ¬r"), r"\lnot p \lor \lnot q \lor \lnot r");
+        assert_eq!(latex("p => q <=> 1 -> ⊥"), r"p \to q \leftrightarrow \top \to \bot");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(latex("p | q & r"), r"p \lor q \land r");
+        assert_eq!(latex("(p | q) & r"), r"(p \lor q) \land r");
+        assert_eq!(latex("~(p ^ q)"), r"\lnot (p \oplus q)");
+        assert_eq!(latex("p -> q -> r"), r"p \to q \to r");
+        assert_eq!(latex("(p -> q) -> r"), r"(p \to q) \to r");
+        assert_eq!(latex("[long_name]"), r"\mathit{long\_name}");
+    }
+
+    #[test]
+    fn bad_formulas() {
+        assert_eq!(logic_err("p &"), "The formula ends too early");
+        assert_eq!(logic_err("(p | q"), "Missing `)`");
+        assert_eq!(logic_err("p q"), "Unexpected `q` after the formula");
+        assert_eq!(logic_err("p & | q"), "Expected a variable, found `|`");
+        assert_eq!(logic_err("p % q"), "Unexpected `%`");
+        assert_eq!(logic_err("a & b & c & d & e & f & g"), "That has 7 variables, the limit is 6");
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |n: usize| format!("{}p{}", "(".repeat(n), ")".repeat(n));
+        assert!(Prop::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Prop::parse(&"~".repeat(MAX_DEPTH)).is_err());
+        assert!(Prop::parse(&format!("{}p", "~".repeat(MAX_DEPTH))).is_ok());
+
+        let too_deep = format!("That's nested too deep, the limit is {} levels", MAX_DEPTH);
+        assert_eq!(logic_err(&nested(MAX_DEPTH + 1)), too_deep);
+        assert_eq!(logic_err(&format!("{}p", "~".repeat(MAX_DEPTH + 1))), too_deep);
+        assert_eq!(logic_err(&format!("p{}", " -> p".repeat(MAX_DEPTH + 1))), too_deep);
+        assert_eq!(logic_err(&nested(100_000)), too_deep);
+    }
+
+    #[test]
+    fn truth_tables() {
+        assert_eq!(
+            truth_table("q -> p").unwrap(),
+            r"\begin{tabular}{cc|c} $p$ & $q$ & $q \to p$ \\ \hline T & T & T \\ T & F & T \\ F & T & F \\ F & F & T \\ \hline \multicolumn{3}{l}{\textit{Satisfiable, true in 3 of 4 rows}} \end{tabular}"
+        );
+    }
+
+    #[test]
+    fn verdicts() {
+        assert!(truth_table("p | ~p").unwrap().contains(r"\textit{Tautology}"));
+        assert!(truth_table("p & ~p").unwrap().contains(r"\textit{Contradiction}"));
+        assert!(truth_table("(p -> q) & ~r").unwrap().contains(r"\textit{Satisfiable, true in 3 of 8 rows}"));
+        assert!(truth_table("true").unwrap().contains(r"\textit{Tautology}"));
+    }
+}
@@ -1,5 +1,16 @@
 use serenity::{
     async_trait,
+    builder::{
+        CreateActionRow,
+        CreateAttachment,
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateInputText,
+        CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+        CreateMessage,
+        CreateModal,
+    },
     framework::standard::{
         CommandResult,
         macros::{
@@ -8,50 +19,84 @@ use serenity::{
         },
         Args,
     },
-    http,
     model::{
         channel::Message,
         event::MessageUpdateEvent,
-        interactions::{
+        application::{
+            ActionRowComponent,
+            InputTextStyle,
             Interaction,
-            InteractionResponseType,
-            InteractionApplicationCommandCallbackDataFlags,
+            ModalInteraction,
         },
         id::MessageId,
     },
     prelude::*,
 };
 use std::{
-    borrow::Cow,
+    io::{
+        self,
+        Write,
+    },
     collections::VecDeque,
     sync::Arc,
     pin::Pin,
+    time::Duration,
 };
 use futures::Future;
-#[allow(unused_imports)] use usvg::SystemFontDB;
-use usvg;
-use tiny_skia::Color;
-use tempfile;
+use zip;
 use crate::{
     botmods::{
         errors,
         errors::err_msg,
+        mathspeak,
+        settings::get_settings,
+        snippets::save_snippet,
+        symbols::{
+            closest_commands,
+            replace_command,
+        },
+        unicode::{
+            latex_to_unicode,
+            unicode_to_latex,
+            escape_markdown,
+        },
         utils::{
+            can_manage_messages,
+            delete_responses,
             loading_msg,
+            output_level,
+            output_target,
+            OutputLevel,
+            strip_flags,
             Buttons,
             BotModule,
             Editable,
             Interactable,
             push_to_editables,
-            push_to_interactables
+            push_to_interactables,
         },
     },
+    render::{
+        compile_pdf,
+        compile_svg,
+        latex_file,
+        rasterize,
+        RenderError,
+        RenderTimings,
+        SCALE,
+        MIN_SCALE,
+        MAX_SCALE,
+    },
+    lint::{
+        lint,
+        Severity,
+    },
     PREFIX,
     Interactables,
     Editables
 };
+pub use crate::render::MathText;
 use regex::Regex;
-use tokio::process::Command;
 use serde::{
     Serialize,
     Deserialize
@@ -62,9 +107,10 @@ lazy_static!(
     pub static ref MOD_MARKUP: BotModule = BotModule {
         command_group: &MARKUP_GROUP,
         command_pattern: vec![
-            Regex::new(format!(r"^{}latex .*$", PREFIX.as_str()).as_str()).unwrap(),
-            Regex::new(format!(r"^{}ascii .*$", PREFIX.as_str()).as_str()).unwrap(),
-            Regex::new(r"(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap()
+            Regex::new(format!(r"(?s)^{}latex .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}ascii .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(r"(?s)(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap(),
+            Regex::new(format!(r"(?s)^{}unicode .*$", PREFIX.as_str()).as_str()).unwrap(),
         ],
         editors: vec![
            edit_handler_wrap,
@@ -80,11 +126,9 @@ lazy_static!(
 
 #[group]
 #[summary = "Math formatting commands"]
-#[commands(ascii, latex)]
+#[commands(ascii, latex, unicode, renderstats)]
 struct Markup;
 
-const SCALE: u32 = 8;
-
 #[derive(PartialEq)]
 pub enum CmdType {
     Ascii,
@@ -94,14 +138,14 @@ pub enum CmdType {
 
 lazy_static!{
     pub static ref EDITMATCH: Vec<(Regex, CmdType)> = vec![
-        (Regex::new(format!(r"^{}latex (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Latex),
-        (Regex::new(format!(r"^{}ascii (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Ascii),
-        (Regex::new(r"(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap(), CmdType::Inline),
+        (Regex::new(format!(r"(?s)^{}latex (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Latex),
+        (Regex::new(format!(r"(?s)^{}ascii (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Ascii),
+        (Regex::new(r"(?s)(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap(), CmdType::Inline),
     ];
     pub static ref COMPMATCH: Vec<Regex> = vec![
-        Regex::new(format!(r"^{}latex .*$", PREFIX.as_str()).as_str()).unwrap(),
-        Regex::new(format!(r"^{}ascii .*$", PREFIX.as_str()).as_str()).unwrap(),
-        Regex::new(r"(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap(),
+        Regex::new(format!(r"(?s)^{}latex .*$", PREFIX.as_str()).as_str()).unwrap(),
+        Regex::new(format!(r"(?s)^{}ascii .*$", PREFIX.as_str()).as_str()).unwrap(),
+        Regex::new(r"(?s)(\$.*\$)|(\\[.*\\])|(\\(.*\\))").unwrap(),
     ];
 }
 
@@ -115,50 +159,192 @@ fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box
     Box::pin(edit_handler(ctx, msg_upd_event))
 }
 
-async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+const FLAGS: [&str; 2] = ["--spoiler", "--raw"];
+/// Discord's limit on a modal text input
+const MAX_MODAL_SOURCE: usize = 4000;
+/// Discord's limit on an attachment description
+const MAX_ATTACHMENT_ALT: usize = 1024;
+/// Keeps Read aloud under the message length limit
+const MAX_MESSAGE_ALT: usize = 1993;
+
+pub struct ParsedInput {
+    pub cmd_type: CmdType,
+    pub text: MathText,
+    pub spoiler: bool,
+    pub raw: bool,
+}
+
+/// Delimiters the inline watcher knows, servers can pick which of them count
+pub const INLINE_DELIMITERS: [&str; 4] = ["$", "$$", r"\(", r"\["];
+
+/// What a channel renders from messages that aren't commands
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum InlineMode {
+    Off,
+    Delimited(Vec<String>),
+    /// Math channels, where every message is math
+    Everything,
+}
+
+fn has_inline<S: AsRef<str>>(content: &str, delimiters: &[S]) -> bool {
+    lazy_static! {
+        static ref DELIMITER_RES: Vec<(&'static str, fancy_regex::Regex)> = vec![
+            ("$", fancy_regex::Regex::new(r"(?<!\$)\$(?![\s$]).+?(?<![\s$])\$(?!\$)").unwrap()),
+            ("$$", fancy_regex::Regex::new(r"\$\$(?![\s$]).+?(?<![\s$])\$\$").unwrap()),
+            (r"\(", fancy_regex::Regex::new(r"\\\(.+?\\\)").unwrap()),
+            (r"\[", fancy_regex::Regex::new(r"\\\[.+?\\\]").unwrap()),
+        ];
+    }
+
+    DELIMITER_RES.iter()
+        .filter(|(d, _)| delimiters.iter().any(|s| s.as_ref() == *d))
+        .any(|(_, r)| r.is_match(content).unwrap_or(false))
+}
+
+/// Works out what kind of snippet a message asks for, and its source
+pub fn parse_content(content: &str) -> Option<ParsedInput> {
+    parse_delimited(content, &INLINE_DELIMITERS)
+}
+
+fn parse_delimited<S: AsRef<str>>(content: &str, delimiters: &[S]) -> Option<ParsedInput> {
     lazy_static! {
-        static ref INLINE_RE: fancy_regex::Regex = fancy_regex::Regex::new(r"((\${1,2})(?![\s$]).+(?<![\s$])\2)|(\\[.*\\])|(\\(.*\\))").unwrap();
-        static ref LATEX_RE: Regex = Regex::new(format!(r"^{}latex (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-        static ref ASCII_RE: Regex = Regex::new(format!(r"^{}ascii (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        static ref SPOILER_RE: Regex = Regex::new(r"(?s)\|\|(?P<inner>.+?)\|\|").unwrap();
+        static ref IGNORE_RE: Regex = Regex::new(r"¯\\\\_(ツ)\\_/¯").unwrap();
+        static ref LATEX_RE: Regex = Regex::new(format!(r"(?s)^{}latex (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        static ref ASCII_RE: Regex = Regex::new(format!(r"(?s)^{}ascii (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    };
+
+    if let Some(c) = LATEX_RE.captures(content) {
+        let (flags, args) = strip_flags(c.name("args").unwrap().as_str(), &FLAGS);
+        Some(ParsedInput {
+            cmd_type: CmdType::Latex,
+            text: MathText::Latex(String::from(args)),
+            spoiler: flags.iter().any(|f| f == "--spoiler"),
+            raw: flags.iter().any(|f| f == "--raw"),
+        })
+    } else if let Some(c) = ASCII_RE.captures(content) {
+        let (flags, args) = strip_flags(c.name("args").unwrap().as_str(), &FLAGS);
+        Some(ParsedInput {
+            cmd_type: CmdType::Ascii,
+            text: MathText::AsciiMath(String::from(args)),
+            spoiler: flags.iter().any(|f| f == "--spoiler"),
+            raw: flags.iter().any(|f| f == "--raw"),
+        })
+    } else if has_inline(content, delimiters) && !IGNORE_RE.is_match(content) {
+        // Math inside ||spoiler|| markup gets its render hidden too
+        let spoiler = SPOILER_RE.captures_iter(content).any(|c| has_inline(c.name("inner").unwrap().as_str(), delimiters));
+        let text = if spoiler {
+            SPOILER_RE.replace_all(content, "$inner").to_string()
+        } else {
+            String::from(content)
+        };
+
+        Some(ParsedInput {
+            cmd_type: CmdType::Inline,
+            text: MathText::Latex(text),
+            spoiler,
+            raw: false,
+        })
+    } else {
+        None
+    }
+}
+
+/// Matches the start of anything that isn't led by `prefix`, the regex crate has no lookahead to say that
+fn unprefixed_pattern(prefix: &str) -> String {
+    let chars: Vec<String> = prefix.chars().map(|c| regex::escape(&c.to_string())).collect();
+
+    // A string that stops partway through the prefix doesn't start with it either
+    let mut pattern = match chars.last() {
+        Some(c) => format!("[^{}]", c),
+        None => return String::from("[^\\s\\S]"),
     };
+    for c in chars.iter().rev().skip(1) {
+        pattern = format!("[^{c}]|{c}(?:$|{})", pattern, c = c);
+    }
+
+    format!("(?:{})", pattern)
+}
+
+/// Like `parse_content`, with inline math found the way the channel wants it
+pub fn parse_message(content: &str, mode: &InlineMode) -> Option<ParsedInput> {
+    match mode {
+        InlineMode::Off => parse_delimited::<&str>(content, &[]),
+        InlineMode::Delimited(d) => parse_delimited(content, d),
+        InlineMode::Everything => match parse_content(content) {
+            Some(p) => Some(p),
+            None if !content.trim().is_empty() && !content.starts_with(PREFIX.as_str()) => Some(ParsedInput {
+                cmd_type: CmdType::Inline,
+                text: MathText::Latex(String::from(content)),
+                spoiler: false,
+                raw: false,
+            }),
+            None => None,
+        },
+    }
+}
+
+async fn inline_mode(ctx: &Context, msg: &Message) -> InlineMode {
+    let settings = get_settings(ctx, msg.guild_id).await;
+    if settings.math_channels.contains(&msg.channel_id.get()) {
+        InlineMode::Everything
+    } else if settings.inline_in(msg.channel_id) {
+        InlineMode::Delimited(settings.inline_delimiters)
+    } else {
+        InlineMode::Off
+    }
+}
 
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
     let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
         Ok(m) => m,
         Err(_) => {return},
     };
 
-    let new_content = match &msg_upd_event.content {
-        Some(c) => String::from(c),
+    let mode = inline_mode(&ctx, &inp_message).await;
+
+    let parsed = match parse_message(&inp_message.content, &mode) {
+        Some(p) => p,
         None => {return}
     };
 
-    let mut ct: Option<CmdType> = None;
-    let mut arg = "";
+    let mut snip = MathSnip::new(parsed.text, &inp_message).await;
+    snip.spoiler = parsed.spoiler;
+    snip.raw = parsed.raw;
+    snip.inline_mode = mode;
 
-    if INLINE_RE.captures(&new_content).unwrap().is_some() && INLINE_RE.captures(&new_content).unwrap().unwrap().name("args").is_some() {
-        arg = INLINE_RE.captures(&new_content).unwrap().unwrap().name("args").unwrap().as_str();
-        ct = Some(CmdType::Inline);
-    } else if LATEX_RE.captures(&new_content).is_some() && LATEX_RE.captures(&new_content).unwrap().name("args").is_some() {
-        arg = LATEX_RE.captures(&new_content).unwrap().name("args").unwrap().as_str();
-        ct = Some(CmdType::Latex);
-    } else if ASCII_RE.captures(&new_content).is_some() && ASCII_RE.captures(&new_content).unwrap().name("args").is_some() {
-        arg = ASCII_RE.captures(&new_content).unwrap().name("args").unwrap().as_str();
-        ct = Some(CmdType::Ascii);
+    if let Err(e) = send_snip(&ctx, snip).await {
+        eprintln!("Error rendering an edited message:\n{}", e);
     }
-    
-    let new_text = match ct {
-        Some(CmdType::Ascii) => MathText::AsciiMath(String::from(arg)),
-        Some(CmdType::Latex) => MathText::Latex(String::from(arg)),
-        Some(CmdType::Inline) => MathText::Latex(String::from(arg)),
-        _ => {return}
+}
+
+/// Compiles a snippet for a message, posts the render (or the error) and keeps it around for edits and buttons
+pub async fn render_snip(ctx: &Context, inp_message: &Message, text: MathText) -> Result<MathSnip, errors::Error> {
+    send_snip(ctx, MathSnip::new(text, inp_message).await).await
+}
+
+/// Like `render_snip`, for LaTeX the bot wrote itself
+pub async fn render_generated(ctx: &Context, inp_message: &Message, latex: String) -> Result<MathSnip, errors::Error> {
+    send_snip(ctx, MathSnip::generated(latex, inp_message).await).await
+}
+
+async fn send_snip(ctx: &Context, mut snip: MathSnip) -> Result<MathSnip, errors::Error> {
+    let inp_message = snip.inp_message.clone();
+    let lm = loading_msg(ctx, &inp_message.channel_id, &inp_message.author).await?;
+
+    snip.message = match snip.cmpl().await {
+        Ok(_) => {
+            record_timings(ctx, &snip.timings).await;
+            Some(math_msg(ctx, &inp_message.channel_id, Some(&lm), &inp_message.author, &snip).await?)
+        },
+        Err(e) => Some(cmpl_err_msg(ctx, &inp_message.channel_id, Some(&lm), &inp_message.author, &snip, &e).await?),
     };
 
-    let mut new_snip = MathSnip::new(new_text, &inp_message).await;
-    let _cmpl_result = new_snip.cmpl().await;
+    push_to_interactables(ctx, Box::new(snip.clone())).await;
+    push_to_editables(ctx, Box::new(snip.clone())).await;
 
-    math_msg(&ctx, &msg_upd_event.channel_id, None, &msg_upd_event.author.unwrap(), &new_snip).await.unwrap();
-    push_to_interactables(&ctx, Box::new(new_snip.clone())).await;
-    push_to_editables(&ctx, Box::new(new_snip.clone())).await;
+    Ok(snip)
 }
 
 // pub fn component_interaction_handler_wrap(ctx: Context, interaction: Interaction) -> Pin<Box<dyn Future<Output = ()> + Send>> {
@@ -204,20 +390,37 @@ async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
 //     }
 // }
 
-#[cfg_attr(debug_assertions, derive(Debug))]
-#[derive(Serialize, Deserialize, Clone)]
-pub enum MathText {
-    Latex(String),
-    AsciiMath(String)
+/// Totals over every render since startup
+#[derive(Default)]
+pub struct RenderStatsData {
+    pub renders: u64,
+    pub cached: u64,
+    pub totals: RenderTimings,
+    pub slowest: Duration,
 }
 
-impl MathText {
-    pub fn as_str(&self) -> &str {
-        match self {
-            MathText::Latex(s) => s,
-            MathText::AsciiMath(s) => s,
-        }
+pub struct RenderStats;
+
+impl TypeMapKey for RenderStats {
+    type Value = Arc<RwLock<RenderStatsData>>;
+}
+
+async fn record_timings(ctx: &Context, t: &RenderTimings) {
+    let stats_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<RenderStats>().expect("Oops!").clone() //TODO: Error handling
+    };
+
+    let mut stats = stats_lock.write().await;
+    stats.renders += 1;
+    if t.cached {
+        stats.cached += 1;
     }
+    stats.totals.compile += t.compile;
+    stats.totals.svg += t.svg;
+    stats.totals.raster += t.raster;
+    stats.totals.encode += t.encode;
+    stats.slowest = stats.slowest.max(t.total());
 }
 
 #[cfg_attr(debug_assertions, derive(Debug))]
@@ -227,7 +430,22 @@ pub struct MathSnip {
     image: Option<Vec<u8>>,
     inp_message: Message,
     pub message: Option<Message>,
-    error:  Option<String>
+    /// Downloads posted for the snippet, they go when it does
+    #[serde(default)]
+    files: Vec<Message>,
+    error:  Option<String>,
+    dismissed: bool,
+    svg: Option<Vec<u8>>,
+    scale: u32,
+    spoiler: bool,
+    raw: bool,
+    inline_mode: InlineMode,
+    pub timings: RenderTimings,
+    /// Things the linter didn't like but latex might still accept
+    warnings: Vec<String>,
+    /// Commands latex didn't know, with the closest ones it does
+    #[serde(skip)]
+    suggestions: Vec<(String, Vec<&'static str>)>,
 }
 
 impl MathSnip {
@@ -237,125 +455,352 @@ impl MathSnip {
             image: None,
             inp_message: i_msg.clone(),
             message: None,
-            error: None
+            files: vec![],
+            error: None,
+            dismissed: false,
+            svg: None,
+            scale: SCALE,
+            spoiler: parse_content(&i_msg.content).map_or(false, |p| p.spoiler),
+            raw: parse_content(&i_msg.content).map_or(false, |p| p.raw),
+            inline_mode: InlineMode::Off,
+            timings: RenderTimings::default(),
+            warnings: vec![],
+            suggestions: vec![],
         }
     }
+
+    /// A snippet of LaTeX the bot wrote itself, which goes to latex as is
+    pub async fn generated(latex: String, i_msg: &Message) -> MathSnip {
+        let mut snip = MathSnip::new(MathText::Latex(latex), i_msg).await;
+        snip.raw = true;
+        snip
+    }
     
+    pub fn is_inline(&self) -> bool {
+        matches!(parse_message(&self.inp_message.content, &self.inline_mode), Some(ParsedInput { cmd_type: CmdType::Inline, .. }))
+    }
+
     pub async fn cmpl(&mut self) -> Result<(), errors::Error> {
-        let image = match &self.text {
-            MathText::Latex(s) => {
-                let tex_dir = tempfile::TempDir::new()?;
-
-                let dvitex_cli = Command::new("sh")
-                    .arg("-c")
-                    .arg(format!("latex -interaction=nonstopmode -jobname=texput -output-directory={} '\\documentclass[preview,margin=1pt]{{standalone}} \\usepackage[utf8]{{inputenc}} \\usepackage{{mathtools}} \\usepackage{{siunitx}} \\usepackage[version=4]{{mhchem}} \\usepackage{{amsmath}} \\usepackage{{physics}} \\usepackage{{tikz-cd}} \\usepackage{{microtype}} \\usepackage{{xcolor}} \\begin{{document}} \\color{{white}} {} \\end{{document}}'", &tex_dir.path().to_str().unwrap(), &s))
-                    .output()
-                    .await?;
-                
-                if !(dvitex_cli.status.success()) {
-                    let err = String::from_utf8(dvitex_cli.stdout).unwrap();
-                    let useless = Regex::new(r"(?m)(^\(.+$\n)|(^This is .*$\n)|(^Document Class.*$\n)|(^No file.*$\n)|(^.* written on .*\.$\n)|(^\[1\].*$\n)|(^For additional .*$\n)|(^LaTeX2e .*$\n)|(^Preview.*$\n)|(^L3.*$\n)|(^ restricted \\write18 enabled\.$\n)|(^entering extended mode$\n)|(^dalone$\n)|(^.*\.dict\).*$\n)|(^*./usr/share.*$\n)|(^.*\.tex.*$\n)|(^[()]+$\n)").unwrap();
-                    let err = useless.replace_all(&err, "").to_string();
-                    
-                    self.error = Some(err.clone());
-                    return Err(errors::Error::MathError(err));
-                }
-                
-                let dvisvg_cli = Command::new("sh")
-                    .arg("-c")
-                    .arg(format!("dvisvgm --page=1- -n --bbox=\"2pt\" -s {}", &tex_dir.path().join("texput.dvi").to_str().unwrap()))
-                    .output()
-                    .await?;
-                
-                if dvisvg_cli.status.success() {
-                    dvisvg_cli.stdout
-                } else {
-                    return Err(errors::Error::MathError(String::from_utf8(dvisvg_cli.stderr).unwrap()))
-                }
+        let source = match &self.text {
+            MathText::Latex(s) => MathText::Latex(self.latex_source(s)),
+            MathText::AsciiMath(s) => MathText::AsciiMath(s.clone()),
+        };
+
+        // Mistakes the linter is sure about don't need a round trip through latex to report
+        self.warnings = vec![];
+        self.suggestions = vec![];
+        if let MathText::Latex(s) = &source {
+            let (errs, warnings): (Vec<_>, Vec<_>) = lint(s).into_iter().partition(|l| l.severity == Severity::Error);
+            self.warnings = warnings.iter().map(|w| w.to_string()).collect();
+            if !errs.is_empty() {
+                let e = errs.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n");
+                self.error = Some(e.clone());
+                return Err(errors::Error::LintError(e))
+            }
+        }
+
+        self.timings = RenderTimings::default();
+        match compile_svg(&source, &mut self.timings).await {
+            Ok(svg) => self.svg = Some(svg),
+            Err(RenderError::MathError(e)) => {
+                self.suggestions = self.suggest(&e);
+                self.error = Some(e.clone());
+                return Err(errors::Error::MathError(e))
             },
-            MathText::AsciiMath(s) => {
-                let asm = String::from(s);
-                
-                let quote_escape = Regex::new("\"").unwrap();
-                let asm = quote_escape.replace_all(&asm, "\\\"").to_string();
-                
-                let mjax_cli = Command::new("sh")
-                    .arg("-c")
-                    .arg(format!("~/node_modules/.bin/am2svg \"{}\"", &asm))
-                    .output()
-                    .await?;
-                
-                if !(mjax_cli.status.success()) {
-                    let err = String::from_utf8(mjax_cli.stderr).unwrap();
-                    self.error = Some(err.clone());
-                    return Err(errors::Error::MathError(err));
+            Err(e) => {return Err(e.into())},
+        }
+
+        self.raster()
+    }
+
+    /// Guesses at what was meant by each command latex complained about, if it came from the input
+    fn suggest(&self, err: &str) -> Vec<(String, Vec<&'static str>)> {
+        lazy_static! {
+            // TeX shows the context up to the command it choked on, so that's the last thing on the next line
+            static ref UNDEFINED_RE: Regex = Regex::new(r"(?m)^! Undefined control sequence\.\n.*\\(?P<cmd>[A-Za-z]+) ?$").unwrap();
+        }
+
+        let text = match &self.text {
+            MathText::Latex(s) => s,
+            MathText::AsciiMath(_) => {return vec![]},
+        };
+
+        let mut suggestions: Vec<(String, Vec<&'static str>)> = vec![];
+        for c in UNDEFINED_RE.captures_iter(err) {
+            let cmd = String::from(&c["cmd"]);
+            // Only what was typed in, a command from the template can't be fixed from here
+            if suggestions.iter().any(|(s, _)| *s == cmd) || replace_command(text, &cmd, "") == *text {
+                continue
+            }
+            let close = closest_commands(&cmd);
+            if !close.is_empty() {
+                suggestions.push((cmd, close));
+            }
+        }
+        suggestions
+    }
+
+    /// Swaps each unknown command for its closest match
+    fn apply_suggestions(&mut self) {
+        if let MathText::Latex(s) = &self.text {
+            let fixed = self.suggestions.iter().fold(s.clone(), |s, (cmd, close)| replace_command(&s, cmd, close[0]));
+            self.text = MathText::Latex(fixed);
+        }
+    }
+
+    /// Renders the compiled SVG to a PNG at the current scale
+    pub fn raster(&mut self) -> Result<(), errors::Error> {
+        let image = match &self.svg {
+            Some(i) => i,
+            None => {return Err(errors::Error::NoImgError())}
+        };
+
+        self.image = Some(rasterize(image, self.scale, &mut self.timings)?);
+        Ok(())
+    }
+
+    /// Compiles LaTeX snippets to a PDF for downloading, AsciiMath has none
+    pub async fn pdf(&self) -> Result<Option<Vec<u8>>, errors::Error> {
+        let s = match &self.text {
+            MathText::Latex(s) => s,
+            MathText::AsciiMath(_) => {return Ok(None)},
+        };
+
+        Ok(Some(compile_pdf(&self.latex_source(s)).await?))
+    }
+
+    /// What actually goes to latex, with pasted Unicode math swapped for commands unless `--raw` was asked for
+    fn latex_source(&self, s: &str) -> String {
+        if self.raw {
+            String::from(s)
+        } else {
+            unicode_to_latex(s)
+        }
+    }
+
+    /// A zip of everything needed to build the snippet locally, along with what it builds to
+    pub async fn export_zip(&self) -> Result<Vec<u8>, errors::Error> {
+        let mut files: Vec<(&str, Vec<u8>)> = vec![];
+
+        match &self.text {
+            MathText::Latex(s) => {
+                // Guilds can't define macros of their own, so this is everything `cmpl` hands to latex
+                files.push(("snippet.tex", latex_file(&self.latex_source(s), "black").into_bytes()));
+                // The source is still worth having if pdflatex doesn't like it
+                if let Ok(Some(p)) = self.pdf().await {
+                    files.push(("snippet.pdf", p));
                 }
-                
-                let svg_raw = String::from_utf8(mjax_cli.stdout).unwrap();
-                let color_replacer = Regex::new("currentColor").unwrap();
-                let svg_raw = color_replacer.replace_all(&svg_raw, "white");
-                
-                svg_raw.as_bytes().to_vec()
             },
+            MathText::AsciiMath(s) => files.push(("snippet.txt", s.clone().into_bytes())),
+        }
+        if let Some(svg) = &self.svg {
+            files.push(("snippet.svg", svg.clone()));
+        }
+
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        for (name, data) in files {
+            zip.start_file(name, options)?;
+            zip.write_all(&data)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+
+    async fn export_msg(&self, ctx: &Context, for_user: &serenity::model::user::User) -> Result<Message, errors::Error> {
+        let zip = self.export_zip().await?;
+        let prefix = if self.spoiler {"SPOILER_"} else {""};
+
+        let (c_id, _) = output_target(ctx, &self.inp_message.channel_id, for_user).await?;
+        let m = c_id.send_message(&ctx.http, CreateMessage::new()
+            .content("LaTeX project for the snippet above")
+            .add_file(CreateAttachment::bytes(zip, format!("{}snippet.zip", prefix)))
+        ).await?;
+
+        Ok(m)
+    }
+
+    /// The rendered PNG, once compiled
+    pub fn image(&self) -> Option<&Vec<u8>> {
+        self.image.as_ref()
+    }
+
+    /// Plain text version of the snippet, for when there's no image
+    pub fn unicode(&self) -> String {
+        match &self.text {
+            MathText::Latex(s) => latex_to_unicode(s),
+            MathText::AsciiMath(s) => s.clone(),
+        }
+    }
+
+    /// Spoken-math description of the snippet, cut down to `max` bytes
+    pub fn alt_text(&self, max: usize) -> String {
+        let mut alt = mathspeak::speak(&self.text);
+        if alt.len() > max {
+            let mut end = max - 3;
+            while !alt.is_char_boundary(end) {
+                end -= 1;
+            }
+            alt.truncate(end);
+            alt.push_str("...");
+        }
+        alt
+    }
+
+    fn source_msg(&self) -> String {
+        let lang = match &self.text {
+            MathText::Latex(_) => "latex",
+            MathText::AsciiMath(_) => "",
         };
 
-        let mut opt = usvg::Options::default();
-        opt.fontdb.load_system_fonts();
-        opt.fontdb.set_generic_families();
-        
-        let svg_tree = usvg::Tree::from_data(&image, &opt)?;
-        let pixmap_size = svg_tree.svg_node().size.to_screen_size();
-        let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width()*SCALE, pixmap_size.height()*SCALE).unwrap();
-        pixmap.fill(Color::BLACK);
+        let mut source = self.text.as_str().replace("```", "`\u{200b}``");
+        if source.len() > 1950 {
+            let mut end = 1950;
+            while !source.is_char_boundary(end) {
+                end -= 1;
+            }
+            source.truncate(end);
+            source.push_str("...");
+        }
 
-        if let Some(()) = resvg::render(&svg_tree, usvg::FitTo::Zoom(SCALE as f32), pixmap.as_mut()) {
-            self.image = Some(pixmap.encode_png()?);
-        } else {
-            return Err(errors::Error::NoImgError());
+        format!("```{}\n{}\n```", lang, source)
+    }
+
+    async fn download_msg(&self, ctx: &Context, for_user: &serenity::model::user::User) -> Result<Message, errors::Error> {
+        let pdf = self.pdf().await?;
+        let prefix = if self.spoiler {"SPOILER_"} else {""};
+
+        let mut m = CreateMessage::new().content("Files for the snippet above");
+        if let Some(svg) = &self.svg {
+            m = m.add_file(CreateAttachment::bytes(svg.clone(), format!("{}snippet.svg", prefix)));
         }
-        
+        if let Some(p) = pdf {
+            m = m.add_file(CreateAttachment::bytes(p, format!("{}snippet.pdf", prefix)));
+        }
+
+        let (c_id, _) = output_target(ctx, &self.inp_message.channel_id, for_user).await?;
+        let m = c_id.send_message(&ctx.http, m).await?;
+
+        Ok(m)
+    }
+
+    async fn delete_files(&mut self, ctx: &Context) {
+        let files: Vec<_> = self.files.drain(..).map(|m| (m.channel_id, m.id)).collect();
+        delete_responses(ctx, &files).await.ok();
+    }
+
+    /// Renders the source from the Edit modal in place of the current render
+    async fn edit_submit(&mut self, ctx: &Context, modal: ModalInteraction) -> Result<(), errors::Error> {
+        // The button checked this too, but the modal can outlive a permission change
+        if modal.user != self.inp_message.author && !can_manage_messages(modal.member.as_ref()) {
+            modal.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the person who asked for this snippet, or someone who can manage messages, can edit it")
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
+        }
+
+        modal.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let source = modal.data.components.iter()
+            .flat_map(|r| r.components.iter())
+            .find_map(|c| match c {
+                ActionRowComponent::InputText(t) if t.custom_id == "SRC" => t.value.clone(),
+                _ => None,
+            });
+
+        let (source, old_m) = match (source, self.message.clone()) {
+            (Some(s), Some(m)) if !s.trim().is_empty() => (s, m),
+            _ => {return Ok(())}
+        };
+
+        self.text = match &self.text {
+            MathText::Latex(_) => MathText::Latex(source),
+            MathText::AsciiMath(_) => MathText::AsciiMath(source),
+        };
+        self.dismissed = false;
+
+        self.message = match self.cmpl().await {
+            Ok(_) => {
+                record_timings(ctx, &self.timings).await;
+                Some(math_msg(ctx, &old_m.channel_id, Some(&old_m), &self.inp_message.author, self).await?)
+            },
+            Err(e) => Some(cmpl_err_msg(ctx, &old_m.channel_id, Some(&old_m), &self.inp_message.author, self, &e).await?),
+        };
+
         Ok(())
     }
+
+    /// Replaces the editable copy of this snippet, found by its input or its old render
+    async fn update_editables(&self, ctx: &Context, old_m: Option<&Message>) {
+        let editables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        let mut editables = editables_lock.write().await;
+        editables.make_contiguous();
+
+        let mut pos: Option<usize> = None;
+
+        'outer: for (p, i) in editables.iter().enumerate() {
+            for j in i.get_response_message_id() {
+                if let Some(m) = old_m {
+                    if m.id == j {
+                        pos = Some(p);
+                        break 'outer;
+                    }
+                }
+            }
+            if self.inp_message.id == i.get_input_message_id() {
+                pos = Some(p);
+            }
+        }
+
+        if let Some(p) = pos {
+            editables[p] = Box::new(self.clone());
+        }
+    }
 }
 
 #[async_trait]
 impl Editable for MathSnip {
     async fn edit(&mut self, ctx: &Context) -> Result<(), errors::Error> {
-        if let Some(m) = &self.message {
-            lazy_static! {
-                static ref INLINE_RE: fancy_regex::Regex = fancy_regex::Regex::new(r"((\${1,2})(?![\s$]).+(?<![\s$])\2)|(\\[.*\\])|(\\(.*\\))").unwrap();
-                static ref LATEX_RE: Regex = Regex::new(format!(r"^{}latex (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-                static ref ASCII_RE: Regex = Regex::new(format!(r"^{}ascii (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-            };
+        if self.dismissed {
+            return Ok(())
+        }
 
-            let old_m = m.clone();
-
-            m.delete(&ctx).await?;
-
-            if let Ok(im) = self.inp_message.channel_id.message(&ctx, self.inp_message.id).await {
-                if im.content == "" {
-                    return Ok(())
-                } else if INLINE_RE.captures(&im.content).unwrap().is_some() && INLINE_RE.captures(&im.content).unwrap().unwrap().name("args").is_some() {
-                    self.text = MathText::Latex(String::from(INLINE_RE.captures(&im.content).unwrap().unwrap().name("args").unwrap().as_str()));
-                } else if LATEX_RE.captures(&im.content).is_some() && LATEX_RE.captures(&im.content).unwrap().name("args").is_some() {
-                    self.text = MathText::Latex(String::from(LATEX_RE.captures(&im.content).unwrap().name("args").unwrap().as_str()));
-                } else if ASCII_RE.captures(&im.content).is_some() && ASCII_RE.captures(&im.content).unwrap().name("args").is_some() {
-                    self.text = MathText::Latex(String::from(ASCII_RE.captures(&im.content).unwrap().name("args").unwrap().as_str()));
-                } else {
-                    return Ok(())
-                }
+        let old_m = match &self.message {
+            Some(m) => m.clone(),
+            None => {return Ok(())}
+        };
 
-                self.cmpl().await?;
+        old_m.delete(&ctx).await?;
+        self.message = None;
 
-                match math_msg(&ctx, &self.inp_message.channel_id, None, &self.inp_message.author, &self).await {
-                    Ok(m) => {
-                        self.message = Some(m);
-                    },
-                    Err(e) => {
-                        Err(e)?    //TODO: Fix
-                    }
-                }
-            }
+        if let Ok(im) = self.inp_message.channel_id.message(&ctx, self.inp_message.id).await {
+            self.inline_mode = inline_mode(ctx, &im).await;
+            let parsed = match parse_message(&im.content, &self.inline_mode) {
+                Some(p) => p,
+                None => {return Ok(())}
+            };
+            self.text = parsed.text;
+            self.spoiler = parsed.spoiler;
+            self.raw = parsed.raw;
+            self.inp_message = im;
+
+            let lm = loading_msg(ctx, &self.inp_message.channel_id, &self.inp_message.author).await?;
+
+            self.message = match self.cmpl().await {
+                Ok(_) => {
+                    record_timings(ctx, &self.timings).await;
+                    Some(math_msg(&ctx, &self.inp_message.channel_id, Some(&lm), &self.inp_message.author, &self).await?)
+                },
+                Err(e) => Some(cmpl_err_msg(&ctx, &self.inp_message.channel_id, Some(&lm), &self.inp_message.author, &self, &e).await?),
+            };
+        }
 
         let interactables_lock = {
             let data_read = ctx.data.read().await;
@@ -382,7 +827,6 @@ impl Editable for MathSnip {
             }
         }
 
-        }
         return Ok(())
     }
 
@@ -393,26 +837,28 @@ impl Editable for MathSnip {
         }
     }
 
+    fn get_response_messages(&self) -> Vec<(serenity::model::id::ChannelId, MessageId)> {
+        self.message.iter().chain(self.files.iter()).map(|m| (m.channel_id, m.id)).collect()
+    }
+
     fn get_input_message_id(&self) -> serenity::model::id::MessageId {
         self.inp_message.id.clone()
     }
 
     fn get_command_pattern(&self) -> Regex {
-            lazy_static! {
-                static ref INLINE_RE: fancy_regex::Regex = fancy_regex::Regex::new(r"((\${1,2})(?![\s$]).+(?<![\s$])\2)|(\\[.*\\])|(\\(.*\\))").unwrap();
-                static ref LATEX_RE: Regex = Regex::new(format!(r"^{}latex (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-                static ref ASCII_RE: Regex = Regex::new(format!(r"^{}ascii (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-            };
-
-            if INLINE_RE.captures(&self.inp_message.content).unwrap().is_some() && INLINE_RE.captures(&self.inp_message.content).unwrap().unwrap().name("args").is_some() {
-                return MOD_MARKUP.command_pattern[2].clone()
-            } else if LATEX_RE.captures(&self.inp_message.content).is_some() && LATEX_RE.captures(&self.inp_message.content).unwrap().name("args").is_some() {
-                return MOD_MARKUP.command_pattern[0].clone()
-            } else if ASCII_RE.captures(&self.inp_message.content).is_some() && ASCII_RE.captures(&self.inp_message.content).unwrap().name("args").is_some() {
-                return MOD_MARKUP.command_pattern[1].clone()
-            } else {
-                return MOD_MARKUP.command_pattern[2].clone()
-            }
+        match parse_message(&self.inp_message.content, &self.inline_mode).map(|p| p.cmd_type) {
+            // Anything goes in a math channel, except the other modules' commands
+            _ if self.inline_mode == InlineMode::Everything => Regex::new(&format!(
+                    r"(?s)^(?:{}|{}(?:latex|ascii) ).*$",
+                    unprefixed_pattern(PREFIX.as_str()),
+                    regex::escape(PREFIX.as_str()),
+            )).unwrap(),
+            Some(CmdType::Latex) => MOD_MARKUP.command_pattern[0].clone(),
+            Some(CmdType::Ascii) => MOD_MARKUP.command_pattern[1].clone(),
+            Some(CmdType::Inline) => MOD_MARKUP.command_pattern[2].clone(),
+            // Snippets made by other modules, those get re-run by their own editors
+            None => Regex::new(r"[^\s\S]").unwrap(),
+        }
     }
 }
 
@@ -420,58 +866,147 @@ impl Editable for MathSnip {
 impl Interactable for MathSnip {
     async fn interaction_respond(&mut self, ctx: &Context, interaction: Interaction) -> Result<(), errors::Error> {
         let component_interaction = match interaction {
-            Interaction::MessageComponent(m) => m,
+            Interaction::Component(m) => m,
+            Interaction::Modal(m) => {
+                let old_m = self.message.clone();
+                self.edit_submit(ctx, m).await?;
+                self.update_editables(ctx, old_m.as_ref()).await;
+                return Ok(())
+            },
             _ => {return Ok(())}
         };
-        
-        component_interaction.create_interaction_response(ctx, |r|{
-            r.kind(InteractionResponseType::UpdateMessage);
-            r.interaction_response_data(|d|{
-                d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
-                d
-            });
-            r
-        }).await?;
 
-        let old_m = self.message.clone();
+        let button = Buttons::from(component_interaction.data.custom_id.as_str());
 
-        if let Buttons::Delete = Buttons::from(component_interaction.data.custom_id.as_str()) {
-            if self.inp_message.author == component_interaction.user {
-                self.message.as_ref().unwrap().channel_id.delete_message(&ctx, self.message.as_ref().unwrap().id).await?;
-                self.message = None;
-            }
+        if button.author_only() && self.inp_message.author != component_interaction.user {
+            component_interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the person who asked for this snippet can do that")
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
         }
 
-        let editables_lock = {
-            let data_read = ctx.data.read().await;
-            data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
-        };
+        if let Buttons::Edit = button {
+            let user = &component_interaction.user;
+            let source = self.text.as_str();
+
+            let response = if *user != self.inp_message.author && !can_manage_messages(component_interaction.member.as_ref()) {
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the person who asked for this snippet, or someone who can manage messages, can edit it")
+                        .ephemeral(true)
+                )
+            } else if source.chars().count() > MAX_MODAL_SOURCE {
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("This snippet is too long to edit here, edit the message it came from instead")
+                        .ephemeral(true)
+                )
+            } else {
+                let label = match &self.text {
+                    MathText::Latex(_) => "LaTeX",
+                    MathText::AsciiMath(_) => "AsciiMath",
+                };
+                CreateInteractionResponse::Modal(
+                    CreateModal::new("EDT", "Edit snippet").components(vec![
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Paragraph, label, "SRC")
+                                .value(source)
+                                .max_length(MAX_MODAL_SOURCE as u16)
+                        ),
+                    ])
+                )
+            };
 
-        {
-            let mut editables = editables_lock.write().await;
-            editables.make_contiguous();
+            component_interaction.create_response(ctx, response).await?;
+            return Ok(())
+        }
 
-            let mut pos: Option<usize> = None;
-            
-            'outer: for (p, i) in editables.iter().enumerate() {
-                for j in i.get_response_message_id() {
-                    if let Some(m) = &old_m {
-                        if m.id == j {
-                            pos = Some(p);
-                            break 'outer;
-                        }
-                    }
+        if let Buttons::Source | Buttons::ReadAloud | Buttons::Save = button {
+            let content = match button {
+                Buttons::Source => self.source_msg(),
+                Buttons::Save => match save_snippet(ctx, component_interaction.user.id, None, self.text.clone()).await {
+                    Ok(name) => format!("Saved to your snippets as `{}`, render it again with `{}snip {}`", name, PREFIX.as_str(), name),
+                    Err(e) => format!("{}", e),
+                },
+                _ => self.alt_text(MAX_MESSAGE_ALT),
+            };
+
+            component_interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
+        }
+        
+        component_interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let old_m = self.message.clone();
+
+        match button {
+            Buttons::Delete => {
+                // A second press can arrive before the first one's delete has gone through
+                if let Some(m) = self.message.take() {
+                    m.delete(&ctx).await?;
                 }
-                if self.inp_message.id == i.get_input_message_id() {
-                    pos = Some(p);
+                self.delete_files(ctx).await;
+            },
+            Buttons::Dismiss => {
+                if let Some(m) = self.message.take() {
+                    m.delete(&ctx).await?;
                 }
-            }
-
-            if let Some(p) = pos {
-                editables[p] = Box::new(self.clone());
-            }
+                self.delete_files(ctx).await;
+                self.dismissed = true;
+            },
+            Buttons::ZoomIn | Buttons::ZoomOut => {
+                let scale = match button {
+                    Buttons::ZoomIn if self.scale < MAX_SCALE => self.scale + 2,
+                    Buttons::ZoomOut if self.scale > MIN_SCALE => self.scale - 2,
+                    _ => self.scale,
+                };
+
+                if scale != self.scale {
+                    self.scale = scale;
+                    self.raster()?;
+
+                    // Attachments can't be edited, so the render is sent again
+                    let m = self.message.clone().unwrap();
+                    self.message = Some(math_msg(&ctx, &m.channel_id, Some(&m), &self.inp_message.author, &self).await?);
+                }
+            },
+            Buttons::Download => {
+                match self.download_msg(&ctx, &component_interaction.user).await {
+                    Ok(m) => self.files.push(m),
+                    Err(e) => {err_msg(&ctx, &self.inp_message.channel_id, None, Some(&component_interaction.user), &e).await?;},
+                }
+            },
+            Buttons::Export => {
+                match self.export_msg(&ctx, &component_interaction.user).await {
+                    Ok(m) => self.files.push(m),
+                    Err(e) => {err_msg(&ctx, &self.inp_message.channel_id, None, Some(&component_interaction.user), &e).await?;},
+                }
+            },
+            Buttons::Fix => {
+                if !self.suggestions.is_empty() {
+                    self.apply_suggestions();
+
+                    let m = self.message.clone().unwrap();
+                    self.message = match self.cmpl().await {
+                        Ok(_) => {
+                            record_timings(ctx, &self.timings).await;
+                            Some(math_msg(&ctx, &m.channel_id, Some(&m), &self.inp_message.author, &self).await?)
+                        },
+                        Err(e) => Some(cmpl_err_msg(&ctx, &m.channel_id, Some(&m), &self.inp_message.author, &self, &e).await?),
+                    };
+                }
+            },
+            _ => {}
         }
 
+        self.update_editables(ctx, old_m.as_ref()).await;
+
         Ok(())
     }
 
@@ -489,90 +1024,194 @@ async fn math_msg(ctx: &Context, c_id: &serenity::model::id::ChannelId, loading_
         m.delete(&ctx.http).await?;
     }
     
-    let buttons = vec![
+    let mut buttons = vec![
         Buttons::Delete,
     ];
+    if math.is_inline() {
+        buttons.push(Buttons::Dismiss);
+    }
+    buttons.extend(vec![
+        Buttons::Source,
+        Buttons::Edit,
+        Buttons::Download,
+        Buttons::ZoomIn,
+        Buttons::ZoomOut,
+        Buttons::ReadAloud,
+        Buttons::Save,
+        Buttons::Export,
+    ]);
+
+    let (c_id, level) = output_target(ctx, c_id, for_user).await?;
+
+    if level == OutputLevel::Text {
+        return unicode_msg(ctx, &c_id, None, for_user, &math.unicode(), math.spoiler).await
+    }
 
-    c_id.send_message(&ctx.http, |m|{
-        m.embed(|e| {
-            e.title("Math snippet");
-            e.description(format!("Input: {}", &math.text.as_str()));
-            e.image("attachment://image.png");
-            e.footer(|f| {
-                if let Some(a) = for_user.avatar_url() {
-                    f.icon_url(a);
-                } else {
-                    f.icon_url(for_user.default_avatar_url());
-                }
-                f.text(format!("Requested by {}#{}", for_user.name, for_user.discriminator));
-                f
-            });
-            e
-        });
-        m.add_file(
-            http::AttachmentType::Bytes {
-                data: Cow::from(math.image.as_ref().unwrap()),
-                filename: String::from("image.png")
-            }
-        );
-        m.components(|c| {
-            Buttons::add_buttons(c, buttons);
-            c
-        });
-        m
-    }).await
+    let timings = if get_settings(ctx, math.inp_message.guild_id).await.render_timings {
+        format!("\n{}", math.timings.footer())
+    } else {
+        String::new()
+    };
+
+    // Embed images can't be spoilered, so those go outside the embed
+    let filename = if math.spoiler {
+        String::from("SPOILER_image.png")
+    } else {
+        String::from("image.png")
+    };
+
+    let mut m = CreateMessage::new();
+    if level == OutputLevel::Embed {
+        let mut e = CreateEmbed::new().title("Math snippet");
+        if math.spoiler {
+            e = e.description(format!("||Input: {}||", &math.text.as_str()));
+        } else {
+            e = e.description(format!("Input: {}", &math.text.as_str()))
+                .image("attachment://image.png");
+        }
+        if !math.warnings.is_empty() {
+            e = e.field("Warnings", math.warnings.join("\n"), false);
+        }
+        m = m.embed(e.footer(CreateEmbedFooter::new(format!("Requested by {}{}", for_user.tag(), timings)).icon_url(for_user.face())));
+    } else {
+        let warnings: String = math.warnings.iter().map(|w| format!("\n⚠ {}", w)).collect();
+        m = m.content(format!("Math snippet requested by {}{}", for_user.tag(), warnings));
+    }
+
+    m = m.components(Buttons::add_buttons(buttons));
+
+    let image = CreateAttachment::bytes(math.image.clone().unwrap(), filename).description(math.alt_text(MAX_ATTACHMENT_ALT));
+    c_id.send_message(&ctx.http, m.add_file(image)).await
+}
+
+async fn unicode_msg(ctx: &Context, c_id: &serenity::model::id::ChannelId, loading_msg: Option<&Message>, for_user: &serenity::model::user::User, text: &str, spoiler: bool) -> Result<Message, SerenityError> {
+    if let Some(m) = loading_msg {
+        m.delete(&ctx.http).await?;
+    }
+
+    let (c_id, level) = output_target(ctx, c_id, for_user).await?;
+
+    let mut text = escape_markdown(text);
+    if text.len() > 1900 {
+        let mut end = 1897;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("...");
+    }
+    if spoiler {
+        text = format!("||{}||", text);
+    }
+
+    if level < OutputLevel::Embed {
+        return c_id.say(&ctx.http, format!("{}\n*Requested by {}*", text, for_user.tag())).await
+    }
+
+    c_id.send_message(&ctx.http, CreateMessage::new().embed(
+        CreateEmbed::new()
+            .title("Math snippet")
+            .description(text)
+            .footer(CreateEmbedFooter::new(format!("Requested by {}", for_user.tag())).icon_url(for_user.face()))
+    )).await
+}
+
+/// Errors in the input get an error embed, anything else falls back to plain text output
+async fn cmpl_err_msg(ctx: &Context, c_id: &serenity::model::id::ChannelId, loading_msg: Option<&Message>, for_user: &serenity::model::user::User, math: &MathSnip, err: &errors::Error) -> Result<Message, SerenityError> {
+    match err {
+        // Inline math is often there by accident, so those errors need to be easy to get rid of
+        errors::Error::MathError(_) | errors::Error::LintError(_) if math.is_inline() || !math.suggestions.is_empty() => snip_err_msg(ctx, c_id, loading_msg, for_user, math, err).await,
+        errors::Error::MathError(_) | errors::Error::LintError(_) => err_msg(ctx, c_id, loading_msg, Some(for_user), err).await,
+        _ => unicode_msg(ctx, c_id, loading_msg, for_user, &math.unicode(), math.spoiler).await,
+    }
+}
+
+/// A compile error with buttons, and guesses at the commands latex didn't know if there are any
+async fn snip_err_msg(ctx: &Context, c_id: &serenity::model::id::ChannelId, loading_msg: Option<&Message>, for_user: &serenity::model::user::User, math: &MathSnip, err: &errors::Error) -> Result<Message, SerenityError> {
+    if let Some(m) = loading_msg {
+        m.delete(&ctx.http).await?;
+    }
+
+    let (c_id, level) = output_target(ctx, c_id, for_user).await?;
+
+    let hints = math.suggestions.iter().map(|(cmd, close)| {
+        let close = close.iter().map(|c| format!("`\\{}`", c)).collect::<Vec<String>>().join(" or ");
+        format!("`\\{}` isn't a command, did you mean {}?", cmd, close)
+    }).collect::<Vec<String>>().join("\n");
+
+    let mut buttons = vec![Buttons::Delete];
+    if math.is_inline() {
+        buttons.push(Buttons::Dismiss);
+    }
+    if !math.suggestions.is_empty() {
+        buttons.push(Buttons::Fix);
+    }
+    buttons.push(Buttons::Edit);
+
+    // The hints matter more than the end of the log
+    let mut err_str = format!("There was an error:\n{}", err);
+    let room = 1990 - hints.len().min(1000);
+    if err_str.len() > room {
+        let mut end = room - 3;
+        while !err_str.is_char_boundary(end) {
+            end -= 1;
+        }
+        err_str.truncate(end);
+        err_str.push_str("...");
+    }
+
+    let mut m = CreateMessage::new();
+    if level < OutputLevel::Embed {
+        m = m.content(format!("{}\n{}", err_str, hints).trim_end());
+    } else {
+        let mut e = CreateEmbed::new()
+            .title("Error")
+            .description(&err_str);
+        if !hints.is_empty() {
+            e = e.field("Did you mean", &hints, false);
+        }
+        m = m.embed(e.footer(CreateEmbedFooter::new(format!("Requested by {}", for_user.tag())).icon_url(for_user.face())));
+    }
+
+    c_id.send_message(&ctx.http, m.components(Buttons::add_buttons(buttons))).await
 }
 
 #[command]
 #[description = "Use this command to compile ASCIIMath to a PNG"]
+#[usage = "[--spoiler] <asciimath>"]
 pub async fn ascii(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
-    let lm = loading_msg(ctx, &msg.channel_id).await?;
-
     let asm_raw = match arg.remains() {
         Some(r) => Ok(r),
         None => {
             let err = errors::Error::ArgError(1, 0);
-            err_msg(ctx, &msg.channel_id, Some(&lm), Some(&msg.author), &err).await?;
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
             Err(err)
         },
     }?;
     
-    let mut asm = MathSnip::new(MathText::AsciiMath(String::from(asm_raw)), &msg).await;
-    
-    asm.message = match asm.cmpl().await {
-        Ok(_) => Some(math_msg(ctx, &msg.channel_id, Some(&lm), &msg.author, &asm).await?),
-        Err(e) => Some(err_msg(ctx, &msg.channel_id, Some(&lm), Some(&msg.author), &e).await?),
-    };
+    let (_, asm_raw) = strip_flags(asm_raw, &FLAGS);
     
-    push_to_interactables(&ctx, Box::new(asm.clone())).await;
-    push_to_editables(&ctx, Box::new(asm.clone())).await;
+    render_snip(ctx, msg, MathText::AsciiMath(String::from(asm_raw))).await?;
 
     Ok(())
 }
 
 #[command]
 #[description = "Use this command to compile LaTeX to a PNG"]
+#[usage = "[--spoiler] [--raw] <latex>"]
 pub async fn latex(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
-    let lm = loading_msg(ctx, &msg.channel_id).await?;
-
     let latex_raw = match arg.remains() {
         Some(r) => Ok(r),
         None => {
             let err = errors::Error::ArgError(1, 0);
-            err_msg(ctx, &msg.channel_id, Some(&lm), Some(&msg.author), &err).await?;
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
             Err(err)
         },
     }?;
     
-    let mut latex = MathSnip::new(MathText::Latex(String::from(latex_raw)), &msg).await;
+    let (_, latex_raw) = strip_flags(latex_raw, &FLAGS);
     
-    latex.message = match latex.cmpl().await {
-        Ok(_) => Some(math_msg(ctx, &msg.channel_id, Some(&lm), &msg.author, &latex).await?),
-        Err(e) => Some(err_msg(ctx, &msg.channel_id, Some(&lm), Some(&msg.author), &e).await?),
-    };
-
-    push_to_interactables(&ctx, Box::new(latex.clone())).await;
-    push_to_editables(&ctx, Box::new(latex.clone())).await;
+    render_snip(ctx, msg, MathText::Latex(String::from(latex_raw))).await?;
 
     Ok(())
 }
@@ -582,25 +1221,101 @@ pub fn inline_latex_wrap(ctx: Context, msg: Message) -> Pin<Box<dyn Future<Outpu
 }
 
 async fn inline_latex(ctx: Context, msg: Message) -> CommandResult {
-    let re_tex = fancy_regex::Regex::new(r"((\${1,2})(?![\s$]).+(?<![\s$])\2)|(\\[.*\\])|(\\(.*\\))").unwrap();
-    let re_cmd = Regex::new(format!("{}{}{}", r"(^", PREFIX.as_str(), r"latex.*)|(¯\\\\_(ツ)\\_/¯)").as_str()).unwrap();
-    
-    if re_tex.is_match(&msg.content).unwrap() && !re_cmd.is_match(&msg.content) {
-        let lm = loading_msg(&ctx, &msg.channel_id).await?;
-        
-        let mut latex = MathSnip::new(MathText::Latex(String::from(&msg.content)), &msg).await;
-        latex.cmpl().await?;
+    // Rendering our own output in a math channel would never end
+    if msg.author.bot {
+        return Ok(())
+    }
 
-        math_msg(&ctx, &msg.channel_id, Some(&lm), &msg.author, &latex).await?;
+    let mode = inline_mode(&ctx, &msg).await;
 
-        // latex.message = match latex.cmpl().await {
-        //     Ok(_) => Some(math_msg(&ctx, &msg.channel_id, Some(&lm), &msg.author, &latex).await?),
-        //     Err(e) => Some(err_msg(&ctx, &msg.channel_id, Some(&lm), Some(&msg.author), &e).await?),
-        // };
+    if let Some(ParsedInput { cmd_type: CmdType::Inline, text, .. }) = parse_message(&msg.content, &mode) {
+        // Not worth a DM when nobody asked for it directly
+        if output_level(&ctx, &msg.channel_id).await == OutputLevel::Nothing {
+            return Ok(())
+        }
 
-        push_to_interactables(&ctx, Box::new(latex.clone())).await;
-        push_to_editables(&ctx, Box::new(latex.clone())).await;
+        let mut snip = MathSnip::new(text, &msg).await;
+        snip.inline_mode = mode;
+        send_snip(&ctx, snip).await?;
     };
 
     Ok(())
 }
+
+#[command]
+#[description = "Use this command to convert LaTeX to Unicode text"]
+#[usage = "[--spoiler] <latex>"]
+pub async fn unicode(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let latex_raw = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    let (flags, latex_raw) = strip_flags(latex_raw, &FLAGS);
+
+    unicode_msg(ctx, &msg.channel_id, None, &msg.author, &latex_to_unicode(latex_raw), flags.iter().any(|f| f == "--spoiler")).await?;
+
+    Ok(())
+}
+
+#[command]
+#[owners_only]
+#[help_available(false)]
+#[description = "Average render times for each stage since the bot started"]
+pub async fn renderstats(ctx: &Context, msg: &Message) -> CommandResult {
+    let stats_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<RenderStats>().expect("Oops!").clone() //TODO: Error handling
+    };
+    let stats = stats_lock.read().await;
+
+    // Cached renders skip the first two stages, so those average over fresh renders only
+    let fresh = (stats.renders - stats.cached).max(1) as u32;
+    let all = stats.renders.max(1) as u32;
+
+    msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(
+        CreateEmbed::new()
+            .title("Render stats")
+            .field("Renders", format!("{} ({} cached)", stats.renders, stats.cached), false)
+            .field("Compile", format!("{}ms", (stats.totals.compile / fresh).as_millis()), true)
+            .field("SVG", format!("{}ms", (stats.totals.svg / fresh).as_millis()), true)
+            .field("Raster", format!("{}ms", (stats.totals.raster / all).as_millis()), true)
+            .field("PNG", format!("{}ms", (stats.totals.encode / all).as_millis()), true)
+            .field("Slowest", format!("{}ms", stats.slowest.as_millis()), true)
+    )).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unprefixed(prefix: &str) -> Regex {
+        Regex::new(&format!("(?s)^{}", unprefixed_pattern(prefix))).unwrap()
+    }
+
+    #[test]
+    fn unprefixed_single_char() {
+        let re = unprefixed("!");
+        assert!(re.is_match(r"\frac{1}{2}"));
+        assert!(re.is_match("x !w"));
+        assert!(!re.is_match("!w 2+2"));
+        assert!(!re.is_match(""));
+    }
+
+    #[test]
+    fn unprefixed_long_prefix() {
+        let re = unprefixed("w.");
+        assert!(re.is_match("x^2"));
+        assert!(re.is_match("w"));
+        assert!(re.is_match("wx"));
+        assert!(re.is_match("w\n."));
+        assert!(!re.is_match("w.latex x"));
+        assert!(!re.is_match("w."));
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use crate::botmods::markup::MathText;
+use lazy_static;
+
+lazy_static!{
+    static ref SYMBOLS: HashMap<&'static str, &'static str> = vec![
+        ("alpha", "alpha"), ("beta", "beta"), ("gamma", "gamma"), ("delta", "delta"),
+        ("epsilon", "epsilon"), ("varepsilon", "epsilon"), ("zeta", "zeta"), ("eta", "eta"),
+        ("theta", "theta"), ("vartheta", "theta"), ("iota", "iota"), ("kappa", "kappa"),
+        ("lambda", "lambda"), ("mu", "mu"), ("nu", "nu"), ("xi", "xi"), ("pi", "pi"),
+        ("varpi", "pi"), ("rho", "rho"), ("varrho", "rho"), ("sigma", "sigma"),
+        ("varsigma", "sigma"), ("tau", "tau"), ("upsilon", "upsilon"), ("phi", "phi"),
+        ("varphi", "phi"), ("chi", "chi"), ("psi", "psi"), ("omega", "omega"),
+        ("Gamma", "capital Gamma"), ("Delta", "capital Delta"), ("Theta", "capital Theta"),
+        ("Lambda", "capital Lambda"), ("Xi", "capital Xi"), ("Pi", "capital Pi"),
+        ("Sigma", "capital Sigma"), ("Upsilon", "capital Upsilon"), ("Phi", "capital Phi"),
+        ("Psi", "capital Psi"), ("Omega", "capital Omega"),
+        ("int", "integral"), ("iint", "double integral"), ("iiint", "triple integral"),
+        ("oint", "contour integral"), ("sum", "sum"), ("prod", "product"), ("lim", "limit"),
+        ("infty", "infinity"), ("oo", "infinity"), ("partial", "partial"), ("nabla", "nabla"),
+        ("cdot", "dot"), ("times", "times"), ("xx", "times"), ("div", "divided by"),
+        ("pm", "plus or minus"), ("mp", "minus or plus"), ("leq", "less than or equal to"),
+        ("le", "less than or equal to"), ("geq", "greater than or equal to"),
+        ("ge", "greater than or equal to"), ("neq", "not equal to"), ("ne", "not equal to"),
+        ("approx", "approximately equals"), ("equiv", "is equivalent to"), ("sim", "tilde"),
+        ("propto", "proportional to"), ("to", "right arrow"), ("rightarrow", "right arrow"),
+        ("leftarrow", "left arrow"), ("Rightarrow", "implies"), ("implies", "implies"),
+        ("iff", "if and only if"), ("Leftrightarrow", "if and only if"), ("mapsto", "maps to"),
+        ("in", "element of"), ("notin", "not an element of"), ("subset", "subset of"),
+        ("subseteq", "subset of or equal to"), ("cup", "union"), ("cap", "intersection"),
+        ("emptyset", "empty set"), ("varnothing", "empty set"), ("forall", "for all"),
+        ("exists", "there exists"), ("neg", "not"), ("lnot", "not"), ("land", "and"),
+        ("wedge", "and"), ("lor", "or"), ("vee", "or"), ("cdots", "midline ellipsis"),
+        ("ldots", "ellipsis"), ("dots", "ellipsis"), ("prime", "prime"), ("circ", "ring"),
+        ("degree", "degree"), ("angle", "angle"), ("perp", "perpendicular to"),
+        ("parallel", "parallel to"), ("hbar", "h bar"), ("ell", "script l"),
+        ("sin", "sine"), ("cos", "cosine"), ("tan", "tangent"), ("sec", "secant"),
+        ("csc", "cosecant"), ("cot", "cotangent"), ("arcsin", "arc sine"),
+        ("arccos", "arc cosine"), ("arctan", "arc tangent"), ("sinh", "hyperbolic sine"),
+        ("cosh", "hyperbolic cosine"), ("tanh", "hyperbolic tangent"), ("log", "log"),
+        ("ln", "natural log"), ("exp", "exp"), ("det", "determinant"), ("max", "max"),
+        ("min", "min"), ("sup", "supremum"), ("inf", "infimum"), ("gcd", "gcd"),
+        ("langle", "left angle bracket"), ("rangle", "right angle bracket"),
+        ("lfloor", "left floor"), ("rfloor", "right floor"), ("lceil", "left ceiling"),
+        ("rceil", "right ceiling"), ("{", "left brace"), ("}", "right brace"),
+        ("|", "double vertical bar"), ("%", "percent"), ("$", "dollar sign"), ("_", "underscore"),
+    ].into_iter().collect();
+
+    static ref CHARS: HashMap<char, &'static str> = vec![
+        ('+', "plus"), ('-', "minus"), ('=', "equals"), ('<', "less than"),
+        ('>', "greater than"), ('(', "left parenthesis"), (')', "right parenthesis"),
+        ('[', "left bracket"), (']', "right bracket"), (',', "comma"), ('!', "factorial"),
+        ('\'', "prime"), ('/', "slash"), ('|', "vertical bar"), ('*', "times"),
+        (':', "colon"), (';', "semicolon"), ('.', "point"),
+    ].into_iter().collect();
+}
+
+/// A MathSpeak-style English description of a snippet, for screen readers
+pub fn speak(text: &MathText) -> String {
+    let words = match text {
+        MathText::Latex(s) => LatexSpeaker::new(s).speak(),
+        MathText::AsciiMath(s) => AsciiSpeaker::new(s).speak(),
+    };
+
+    words.join(" ")
+}
+
+#[derive(Clone, PartialEq)]
+enum TexToken {
+    Cmd(String),
+    Char(char),
+    Number(String),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Shift,
+}
+
+fn tex_tokens(s: &str) -> Vec<TexToken> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphabetic() {
+                        name.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if name.is_empty() {
+                    if let Some(n) = chars.next() {
+                        name.push(n);
+                    }
+                }
+                tokens.push(TexToken::Cmd(name));
+            },
+            '{' => tokens.push(TexToken::Open),
+            '}' => tokens.push(TexToken::Close),
+            '^' => tokens.push(TexToken::Sup),
+            '_' => tokens.push(TexToken::Sub),
+            '$' => tokens.push(TexToken::Shift),
+            '0'..='9' => {
+                let mut num = c.to_string();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_digit() || (n == '.' && num.chars().last().unwrap().is_ascii_digit()) {
+                        num.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(TexToken::Number(num));
+            },
+            // Spaces only matter inside \text, runs of them are kept as one
+            c if c.is_whitespace() => {
+                if tokens.last() != Some(&TexToken::Char(' ')) {
+                    tokens.push(TexToken::Char(' '));
+                }
+            },
+            c => tokens.push(TexToken::Char(c)),
+        }
+    }
+
+    tokens
+}
+
+struct LatexSpeaker {
+    tokens: Vec<TexToken>,
+    pos: usize,
+    raw: String,
+}
+
+impl LatexSpeaker {
+    fn new(s: &str) -> LatexSpeaker {
+        LatexSpeaker {
+            tokens: tex_tokens(s),
+            pos: 0,
+            raw: String::from(s),
+        }
+    }
+
+    fn speak(&mut self) -> Vec<String> {
+        // Inline snippets are prose with math in them, only the math gets spoken out
+        if self.raw.contains('$') || self.raw.contains("\\(") || self.raw.contains("\\[") {
+            return self.speak_mixed();
+        }
+
+        let mut words = vec![];
+        while self.pos < self.tokens.len() {
+            words.extend(self.atom());
+        }
+        words
+    }
+
+    fn speak_mixed(&mut self) -> Vec<String> {
+        lazy_static! {
+            static ref MATH_RE: regex::Regex = regex::Regex::new(r"(?s)\$\$(.+?)\$\$|\$(.+?)\$|\\\[(.+?)\\\]|\\\((.+?)\\\)").unwrap();
+        };
+
+        let mut words: Vec<String> = vec![];
+        let mut last = 0;
+
+        for c in MATH_RE.captures_iter(&self.raw) {
+            let m = c.get(0).unwrap();
+            words.extend(self.raw[last..m.start()].split_whitespace().map(String::from));
+
+            let inner = (1..5).filter_map(|i| c.get(i)).next().unwrap().as_str();
+            let mut inner_speaker = LatexSpeaker {
+                tokens: tex_tokens(inner),
+                pos: 0,
+                raw: String::new(),
+            };
+            words.extend(inner_speaker.speak());
+
+            last = m.end();
+        }
+        words.extend(self.raw[last..].split_whitespace().map(String::from));
+
+        words
+    }
+
+    fn next_token(&mut self) -> Option<TexToken> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn peek(&self) -> Option<&TexToken> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Everything up to the matching close brace
+    fn group(&mut self) -> Vec<String> {
+        let mut words = vec![];
+        while let Some(t) = self.peek() {
+            if *t == TexToken::Close {
+                self.pos += 1;
+                break;
+            }
+            words.extend(self.atom());
+        }
+        words
+    }
+
+    /// A command argument, either a group or a single token
+    fn arg(&mut self) -> Vec<String> {
+        if let Some(TexToken::Char(' ')) = self.peek() {
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            Some(TexToken::Open) => {
+                self.pos += 1;
+                self.group()
+            },
+            Some(_) => self.atom(),
+            None => vec![],
+        }
+    }
+
+    /// Raw text of a group, for \text and friends
+    fn text_arg(&mut self) -> Vec<String> {
+        if let Some(TexToken::Char(' ')) = self.peek() {
+            self.pos += 1;
+        }
+
+        if let Some(TexToken::Open) = self.peek() {
+            self.pos += 1;
+        } else {
+            return self.atom();
+        }
+
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(t) = self.next_token() {
+            match t {
+                TexToken::Close if depth == 0 => break,
+                TexToken::Close => depth -= 1,
+                TexToken::Open => depth += 1,
+                TexToken::Char(c) => text.push(c),
+                TexToken::Number(n) => text.push_str(&n),
+                TexToken::Cmd(c) => text.push_str(&c),
+                _ => {},
+            }
+        }
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn script(&mut self, start: &str) -> Vec<String> {
+        let arg = self.arg();
+
+        if start == "Superscript" {
+            match arg.iter().map(|s| s.as_str()).collect::<Vec<&str>>().as_slice() {
+                ["2"] => return vec![String::from("squared")],
+                ["3"] => return vec![String::from("cubed")],
+                ["prime"] => return arg,
+                _ => {},
+            }
+        }
+
+        let mut words = vec![String::from(start)];
+        words.extend(arg);
+        words.push(String::from("Baseline"));
+        words
+    }
+
+    fn atom(&mut self) -> Vec<String> {
+        let t = match self.next_token() {
+            Some(t) => t,
+            None => {return vec![]},
+        };
+
+        match t {
+            TexToken::Cmd(c) => self.command(&c),
+            TexToken::Char(c) => match CHARS.get(&c) {
+                Some(w) => vec![String::from(*w)],
+                None if c.is_alphanumeric() => vec![c.to_string()],
+                None => vec![],
+            },
+            TexToken::Number(n) => vec![n],
+            TexToken::Open => self.group(),
+            TexToken::Close | TexToken::Shift => vec![],
+            TexToken::Sup => self.script("Superscript"),
+            TexToken::Sub => self.script("Subscript"),
+        }
+    }
+
+    fn command(&mut self, c: &str) -> Vec<String> {
+        let mut words = vec![];
+
+        match c {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                words.push(String::from("StartFraction"));
+                words.extend(self.arg());
+                words.push(String::from("Over"));
+                words.extend(self.arg());
+                words.push(String::from("EndFraction"));
+            },
+            "binom" => {
+                words.push(String::from("StartBinomial"));
+                words.extend(self.arg());
+                words.push(String::from("Choose"));
+                words.extend(self.arg());
+                words.push(String::from("EndBinomial"));
+            },
+            "sqrt" => {
+                if let Some(TexToken::Char('[')) = self.peek() {
+                    self.pos += 1;
+                    words.push(String::from("RootIndex"));
+                    while let Some(t) = self.peek() {
+                        if *t == TexToken::Char(']') {
+                            self.pos += 1;
+                            break;
+                        }
+                        words.extend(self.atom());
+                    }
+                }
+                words.push(String::from("StartRoot"));
+                words.extend(self.arg());
+                words.push(String::from("EndRoot"));
+            },
+            "text" | "textrm" | "textbf" | "textit" | "mbox" | "operatorname" | "mathrm" => {
+                words.extend(self.text_arg());
+            },
+            "mathbf" | "mathit" | "mathsf" | "mathcal" | "mathbb" | "mathfrak" | "boldsymbol" | "displaystyle" | "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "," | ";" | "!" | "quad" | "qquad" | " " => {},
+            "vec" => {
+                words.extend(self.arg());
+                words.push(String::from("vector"));
+            },
+            "hat" | "bar" | "dot" | "ddot" | "tilde" | "overline" => {
+                words.extend(self.arg());
+                words.push(String::from(match c {
+                    "hat" => "hat",
+                    "dot" => "dot",
+                    "ddot" => "double dot",
+                    "tilde" => "tilde",
+                    _ => "bar",
+                }));
+            },
+            "\\" => words.push(String::from("new line")),
+            "begin" | "end" => {
+                let env = self.text_arg().join("");
+                words.push(format!("{} {}", if c == "begin" {"Start"} else {"End"}, env));
+            },
+            c => match SYMBOLS.get(c) {
+                Some(w) => words.push(String::from(*w)),
+                None => words.push(String::from(c)),
+            },
+        }
+
+        words
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum AsciiToken {
+    Word(String),
+    Symbol(String),
+    Number(String),
+    Open(char),
+    Close(char),
+}
+
+fn ascii_tokens(s: &str) -> Vec<AsciiToken> {
+    const SYMBOLS: [&str; 13] = ["<=>", "<=", ">=", "!=", "->", "=>", "+-", "**", "^", "_", "/", "+", "-"];
+
+    let mut tokens = vec![];
+    let chars: Vec<char> = s.chars().collect();
+    let mut i = 0;
+
+    'outer: while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if "([{".contains(c) {
+            tokens.push(AsciiToken::Open(c));
+            i += 1;
+            continue;
+        }
+        if ")]}".contains(c) {
+            tokens.push(AsciiToken::Close(c));
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let mut num = String::new();
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                num.push(chars[i]);
+                i += 1;
+            }
+            tokens.push(AsciiToken::Number(num));
+            continue;
+        }
+        if c.is_alphabetic() {
+            let mut word = String::new();
+            while i < chars.len() && chars[i].is_alphabetic() {
+                word.push(chars[i]);
+                i += 1;
+            }
+            tokens.push(AsciiToken::Word(word));
+            continue;
+        }
+
+        let rest: String = chars[i..].iter().collect();
+        for sym in SYMBOLS.iter() {
+            if rest.starts_with(sym) {
+                tokens.push(AsciiToken::Symbol(String::from(*sym)));
+                i += sym.chars().count();
+                continue 'outer;
+            }
+        }
+
+        tokens.push(AsciiToken::Symbol(c.to_string()));
+        i += 1;
+    }
+
+    tokens
+}
+
+struct AsciiSpeaker {
+    tokens: Vec<AsciiToken>,
+    pos: usize,
+}
+
+impl AsciiSpeaker {
+    fn new(s: &str) -> AsciiSpeaker {
+        AsciiSpeaker {
+            tokens: ascii_tokens(s),
+            pos: 0,
+        }
+    }
+
+    fn speak(&mut self) -> Vec<String> {
+        let mut words = vec![];
+        while self.pos < self.tokens.len() {
+            words.extend(self.expression());
+            // Unbalanced closing brackets
+            if let Some(AsciiToken::Close(c)) = self.tokens.get(self.pos) {
+                words.push(String::from(*CHARS.get(c).unwrap_or(&"right brace")));
+                self.pos += 1;
+            }
+        }
+        words
+    }
+
+    fn is_symbol(&self, s: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(AsciiToken::Symbol(x)) if x == s)
+    }
+
+    fn expression(&mut self) -> Vec<String> {
+        let mut words = vec![];
+        while let Some(t) = self.tokens.get(self.pos) {
+            if let AsciiToken::Close(_) = t {
+                break;
+            }
+            words.extend(self.intermediate());
+        }
+        words
+    }
+
+    fn intermediate(&mut self) -> Vec<String> {
+        let (mut words, bracket) = self.simple();
+
+        // Brackets only get dropped when the group is an argument
+        let is_arg = self.is_symbol("_") || self.is_symbol("^") || self.is_symbol("/");
+        if let (Some(o), false) = (bracket, is_arg) {
+            let close = match o {
+                '(' => ')',
+                '[' => ']',
+                _ => '}',
+            };
+            words.insert(0, String::from(*CHARS.get(&o).unwrap_or(&"left brace")));
+            words.push(String::from(*CHARS.get(&close).unwrap_or(&"right brace")));
+        }
+
+        if self.is_symbol("_") {
+            self.pos += 1;
+            words.push(String::from("Subscript"));
+            words.extend(self.simple().0);
+            words.push(String::from("Baseline"));
+        }
+        if self.is_symbol("^") {
+            self.pos += 1;
+            let (sup, _) = self.simple();
+            match sup.iter().map(|s| s.as_str()).collect::<Vec<&str>>().as_slice() {
+                ["2"] => words.push(String::from("squared")),
+                ["3"] => words.push(String::from("cubed")),
+                _ => {
+                    words.push(String::from("Superscript"));
+                    words.extend(sup);
+                    words.push(String::from("Baseline"));
+                },
+            }
+        }
+        if self.is_symbol("/") {
+            self.pos += 1;
+            let mut frac = vec![String::from("StartFraction")];
+            frac.extend(words);
+            frac.push(String::from("Over"));
+            frac.extend(self.simple().0);
+            frac.push(String::from("EndFraction"));
+            words = frac;
+        }
+
+        words
+    }
+
+    /// Returns the spoken words, and the bracket around them if they were a group
+    fn simple(&mut self) -> (Vec<String>, Option<char>) {
+        let t = match self.tokens.get(self.pos).cloned() {
+            Some(t) => t,
+            None => {return (vec![], None)},
+        };
+        self.pos += 1;
+
+        match t {
+            AsciiToken::Open(o) => {
+                let words = self.expression();
+                if let Some(AsciiToken::Close(_)) = self.tokens.get(self.pos) {
+                    self.pos += 1;
+                }
+                (words, Some(o))
+            },
+            AsciiToken::Close(_) => (vec![], None),
+            AsciiToken::Number(n) => (vec![n], None),
+            AsciiToken::Symbol(s) => {
+                let w = match s.as_str() {
+                    "<=" => "less than or equal to",
+                    ">=" => "greater than or equal to",
+                    "!=" => "not equal to",
+                    "->" => "right arrow",
+                    "=>" => "implies",
+                    "<=>" => "if and only if",
+                    "+-" => "plus or minus",
+                    "**" => "star",
+                    s => match s.chars().next().and_then(|c| CHARS.get(&c)) {
+                        Some(w) => *w,
+                        None => "",
+                    },
+                };
+                (vec![String::from(w)].into_iter().filter(|w| !w.is_empty()).collect(), None)
+            },
+            AsciiToken::Word(w) => {
+                match w.as_str() {
+                    "sqrt" => {
+                        let mut words = vec![String::from("StartRoot")];
+                        words.extend(self.simple().0);
+                        words.push(String::from("EndRoot"));
+                        (words, None)
+                    },
+                    "frac" => {
+                        let mut words = vec![String::from("StartFraction")];
+                        words.extend(self.simple().0);
+                        words.push(String::from("Over"));
+                        words.extend(self.simple().0);
+                        words.push(String::from("EndFraction"));
+                        (words, None)
+                    },
+                    "text" => {
+                        (self.simple().0, None)
+                    },
+                    w => match SYMBOLS.get(w) {
+                        Some(s) => (vec![String::from(*s)], None),
+                        None => (w.chars().map(|c| c.to_string()).collect(), None),
+                    },
+                }
+            },
+        }
+    }
+}
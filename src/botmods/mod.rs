@@ -6,11 +6,29 @@ pub mod markup;
 pub mod wolfram;
 pub mod utils;
 pub mod logging;
+pub mod settings;
+pub mod mathspeak;
+pub mod unicode;
+pub mod chem;
+pub mod symbols;
+pub mod snippets;
+pub mod tables;
+pub mod logic;
+pub mod steps;
+pub mod document;
 
 lazy_static!(
     pub static ref MODS: Vec<&'static BotModule> = vec![
         &self::general::MOD_GENERAL,
         &self::markup::MOD_MARKUP,
         &self::wolfram::MOD_WOLFRAM,
+        &self::settings::MOD_SETTINGS,
+        &self::chem::MOD_CHEM,
+        &self::symbols::MOD_SYMBOLS,
+        &self::snippets::MOD_SNIPPETS,
+        &self::tables::MOD_TABLES,
+        &self::logic::MOD_LOGIC,
+        &self::steps::MOD_STEPS,
+        &self::document::MOD_DOCUMENT,
     ];
 );
@@ -0,0 +1,448 @@
+use regex::Regex;
+use serenity::{
+    model::{
+        channel::{
+            Channel,
+            Message,
+        },
+        id::{
+            ChannelId,
+            GuildId,
+        },
+    },
+    builder::{
+        CreateEmbed,
+        CreateMessage,
+    },
+    prelude::*,
+    framework::standard::{
+        CommandOptions,
+        CommandResult,
+        macros::{
+            check,
+            command,
+            group,
+        },
+        Args,
+        Reason,
+    },
+};
+use std::{
+    collections::HashMap,
+    num::NonZeroU64,
+    sync::Arc,
+    fs::{
+        File,
+        write,
+    },
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use ron::{
+    de::from_reader,
+    ser::{
+        to_string_pretty,
+        PrettyConfig,
+    },
+};
+use crate::{
+    PREFIX,
+    CONFIG_DIR,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::INLINE_DELIMITERS,
+        utils::BotModule,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_SETTINGS: BotModule = BotModule {
+        command_group: &SETTINGS_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}settings.*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Per-server settings"]
+#[commands(settings)]
+struct Settings;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_delimiters() -> Vec<String> {
+    INLINE_DELIMITERS.iter().map(|d| String::from(*d)).collect()
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GuildSettings {
+    #[serde(default = "default_true")]
+    pub cleanup_on_delete: bool,
+    /// Channels where every message gets rendered
+    #[serde(default)]
+    pub math_channels: Vec<u64>,
+    #[serde(default = "default_true")]
+    pub inline_enabled: bool,
+    #[serde(default = "default_delimiters")]
+    pub inline_delimiters: Vec<String>,
+    /// Channels the inline watcher is limited to, all of them if empty
+    #[serde(default)]
+    pub inline_allow: Vec<u64>,
+    #[serde(default)]
+    pub inline_deny: Vec<u64>,
+    #[serde(default)]
+    pub render_timings: bool,
+}
+
+impl Default for GuildSettings {
+    fn default() -> GuildSettings {
+        GuildSettings {
+            cleanup_on_delete: true,
+            math_channels: vec![],
+            inline_enabled: true,
+            inline_delimiters: default_delimiters(),
+            inline_allow: vec![],
+            inline_deny: vec![],
+            render_timings: false,
+        }
+    }
+}
+
+impl GuildSettings {
+    /// Whether inline math gets rendered in a channel
+    pub fn inline_in(&self, c_id: ChannelId) -> bool {
+        self.inline_enabled
+            && !self.inline_deny.contains(&c_id.get())
+            && (self.inline_allow.is_empty() || self.inline_allow.contains(&c_id.get()))
+    }
+}
+
+pub struct GuildSettingsMap;
+
+impl TypeMapKey for GuildSettingsMap {
+    type Value = Arc<RwLock<HashMap<u64, GuildSettings>>>;
+}
+
+fn settings_path() -> String {
+    format!("{}/guilds.ron", CONFIG_DIR.as_str())
+}
+
+pub fn load_settings() -> HashMap<u64, GuildSettings> {
+    let f = match File::open(settings_path()) {
+        Ok(f) => f,
+        Err(_) => {return HashMap::new()}
+    };
+    match from_reader(f) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed parsing guild settings file:\n{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Settings for a guild, the defaults for DMs and unconfigured guilds
+pub async fn get_settings(ctx: &Context, guild_id: Option<GuildId>) -> GuildSettings {
+    let g_id = match guild_id {
+        Some(g) => g,
+        None => {return GuildSettings::default()}
+    };
+
+    let settings_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<GuildSettingsMap>().expect("Oops!").clone() //TODO: Error handling
+    };
+
+    let settings = settings_lock.read().await;
+    settings.get(&g_id.get()).cloned().unwrap_or_default()
+}
+
+pub async fn set_settings(ctx: &Context, guild_id: GuildId, f: impl FnOnce(&mut GuildSettings)) -> Result<GuildSettings, errors::Error> {
+    let settings_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<GuildSettingsMap>().expect("Oops!").clone() //TODO: Error handling
+    };
+
+    let mut settings = settings_lock.write().await;
+    let s = settings.entry(guild_id.get()).or_default();
+    f(s);
+    let s = s.clone();
+
+    write(settings_path(), to_string_pretty(&*settings, PrettyConfig::new())?)?;
+
+    Ok(s)
+}
+
+fn parse_toggle(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "on" | "true" | "yes" | "enable" => Some(true),
+        "off" | "false" | "no" | "disable" => Some(false),
+        _ => None
+    }
+}
+
+fn toggle_str(b: bool) -> &'static str {
+    if b {"on"} else {"off"}
+}
+
+/// The channel in a `#channel` mention or a bare id
+fn channel_id(s: &str) -> Option<ChannelId> {
+    lazy_static! {
+        static ref CHANNEL_RE: Regex = Regex::new(r"^(<#(?P<mention>\d+)>|(?P<id>\d+))$").unwrap();
+    }
+
+    let caps = CHANNEL_RE.captures(s)?;
+    let id = caps.name("mention").or_else(|| caps.name("id"))?;
+    Some(ChannelId::from(id.as_str().parse::<NonZeroU64>().ok()?))
+}
+
+/// A `#channel` mention or id from this guild, the current channel if there's neither
+async fn parse_channel(ctx: &Context, msg: &Message, s: &str) -> Option<ChannelId> {
+    let c_id = match s.trim() {
+        "" => msg.channel_id,
+        s => channel_id(s)?,
+    };
+
+    let cached = ctx.cache.channel(c_id).map(|c| c.guild_id);
+    let guild_id = match cached {
+        Some(g) => g,
+        // Guilds can still be arriving just after a restart
+        None => match c_id.to_channel(ctx).await {
+            Ok(Channel::Guild(c)) => c.guild_id,
+            _ => {return None}
+        },
+    };
+
+    if Some(guild_id) == msg.guild_id {
+        Some(c_id)
+    } else {
+        None
+    }
+}
+
+fn channels_str(channels: &[u64], empty: &str) -> String {
+    if channels.is_empty() {
+        String::from(empty)
+    } else {
+        channels.iter().map(|c| format!("<#{}>", c)).collect::<Vec<String>>().join(", ")
+    }
+}
+
+/// Adds a channel to a list, or takes it out, without duplicates
+fn toggle_channel(list: &mut Vec<u64>, c_id: ChannelId, on: bool) {
+    list.retain(|c| *c != c_id.get());
+    if on {
+        list.push(c_id.get());
+    }
+}
+
+/// Handles `<key> on|off [#channel]`, adding the channel to or removing it from the key's list
+async fn set_channel_list(ctx: &Context, msg: &Message, key: &str, mut args: Args) -> Result<Option<GuildSettings>, errors::Error> {
+    let toggle = args.single::<String>().ok().and_then(|t| parse_toggle(&t));
+    let channel = parse_channel(ctx, msg, args.rest()).await;
+
+    match (toggle, channel) {
+        (Some(b), Some(c)) => Ok(Some(set_settings(ctx, msg.guild_id.unwrap(), |s| {
+            let l = match key {
+                "mathchannel" => &mut s.math_channels,
+                "inlineallow" => &mut s.inline_allow,
+                _ => &mut s.inline_deny,
+            };
+            toggle_channel(l, c, b);
+        }).await?)),
+        (None, _) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(format!("Expected `on` or `off` for `{}`", key))).await?;
+            Ok(None)
+        },
+        (_, None) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(String::from("Expected a channel from this server"))).await?;
+            Ok(None)
+        },
+    }
+}
+
+async fn settings_msg(ctx: &Context, msg: &Message, s: &GuildSettings) -> CommandResult {
+    let delimiters = s.inline_delimiters.iter().map(|d| format!("`{}`", d)).collect::<Vec<String>>().join(" ");
+    msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(
+        CreateEmbed::new()
+            .title("Server settings")
+            .field("cleanup", format!("`{}`\nDelete bot output when the command is deleted", toggle_str(s.cleanup_on_delete)), false)
+            .field("mathchannel", format!("{}\nChannels where every message is rendered", channels_str(&s.math_channels, "none")), false)
+            .field("inline", format!("`{}`\nRender math found in messages without a command", toggle_str(s.inline_enabled)), false)
+            .field("delimiters", format!("{}\nWhat counts as inline math", if delimiters.is_empty() {String::from("none")} else {delimiters}), false)
+            .field("inlineallow", format!("{}\nThe only channels with inline math", channels_str(&s.inline_allow, "all")), false)
+            .field("inlinedeny", format!("{}\nChannels without inline math", channels_str(&s.inline_deny, "none")), false)
+            .field("timings", format!("`{}`\nShow how long each render took", toggle_str(s.render_timings)), false)
+    )).await?;
+    Ok(())
+}
+
+// Like `required_permissions("MANAGE_GUILD")`, which only knows cached members, and caching
+// members takes the privileged members intent
+#[check]
+#[display_in_help(false)]
+async fn manage_guild(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    let member = msg.member(ctx).await.map_err(|e| Reason::Log(format!("Couldn't get the member: {}", e)))?;
+
+    let perms = match msg.guild(&ctx.cache) {
+        Some(g) => g.member_permissions(&member),
+        None => {return Err(Reason::Log(String::from("The guild isn't cached")))}
+    };
+
+    if perms.manage_guild() {
+        Ok(())
+    } else {
+        Err(Reason::User(String::from("Changing settings needs the Manage Server permission")))
+    }
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(manage_guild)]
+#[description = "View or change the settings for this server"]
+#[usage = "[setting] [value] [#channel]"]
+#[example = "delimiters $$ \\["]
+pub async fn settings(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let g_id = msg.guild_id.unwrap();
+
+    if args.is_empty() {
+        let s = get_settings(ctx, msg.guild_id).await;
+        return settings_msg(ctx, msg, &s).await;
+    }
+
+    let key = args.single::<String>()?;
+    let value = args.rest().to_string();
+
+    let s = match key.as_str() {
+        "cleanup" => {
+            match parse_toggle(&value) {
+                Some(b) => set_settings(ctx, g_id, |s| s.cleanup_on_delete = b).await?,
+                None => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(format!("Expected `on` or `off` for `{}`", key))).await?;
+                    return Ok(())
+                }
+            }
+        },
+        "inline" => {
+            match parse_toggle(&value) {
+                Some(b) => set_settings(ctx, g_id, |s| s.inline_enabled = b).await?,
+                None => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(format!("Expected `on` or `off` for `{}`", key))).await?;
+                    return Ok(())
+                }
+            }
+        },
+        "timings" => {
+            match parse_toggle(&value) {
+                Some(b) => set_settings(ctx, g_id, |s| s.render_timings = b).await?,
+                None => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(format!("Expected `on` or `off` for `{}`", key))).await?;
+                    return Ok(())
+                }
+            }
+        },
+        "delimiters" => {
+            let delimiters: Vec<String> = value.split_whitespace().map(String::from).collect();
+            let unknown = delimiters.iter().find(|d| !INLINE_DELIMITERS.contains(&d.as_str())).cloned();
+            match unknown {
+                None if !delimiters.is_empty() => set_settings(ctx, g_id, |s| s.inline_delimiters = delimiters).await?,
+                bad => {
+                    let known = INLINE_DELIMITERS.iter().map(|d| format!("`{}`", d)).collect::<Vec<String>>().join(" ");
+                    let err = match bad {
+                        Some(d) => format!("`{}` isn't a delimiter, pick from {}", d, known),
+                        None => format!("Expected some of {}, use `inline off` to turn inline math off", known),
+                    };
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(err)).await?;
+                    return Ok(())
+                }
+            }
+        },
+        "mathchannel" | "inlineallow" | "inlinedeny" => {
+            match set_channel_list(ctx, msg, &key, args).await? {
+                Some(s) => s,
+                None => {return Ok(())}
+            }
+        },
+        _ => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SettingError(format!("Unknown setting `{}`", key))).await?;
+            return Ok(())
+        }
+    };
+
+    settings_msg(ctx, msg, &s).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_everywhere_by_default() {
+        let s = GuildSettings::default();
+        assert!(s.inline_in(ChannelId::new(1)));
+        assert_eq!(s.inline_delimiters, INLINE_DELIMITERS);
+    }
+
+    #[test]
+    fn inline_lists() {
+        let mut s = GuildSettings::default();
+        toggle_channel(&mut s.inline_deny, ChannelId::new(1), true);
+        assert!(!s.inline_in(ChannelId::new(1)));
+        assert!(s.inline_in(ChannelId::new(2)));
+
+        // An allow list shuts every other channel out, the deny list still wins
+        toggle_channel(&mut s.inline_allow, ChannelId::new(1), true);
+        toggle_channel(&mut s.inline_allow, ChannelId::new(3), true);
+        assert!(!s.inline_in(ChannelId::new(1)));
+        assert!(!s.inline_in(ChannelId::new(2)));
+        assert!(s.inline_in(ChannelId::new(3)));
+
+        s.inline_enabled = false;
+        assert!(!s.inline_in(ChannelId::new(3)));
+    }
+
+    #[test]
+    fn toggling_channels() {
+        let mut l = vec![];
+        toggle_channel(&mut l, ChannelId::new(1), true);
+        toggle_channel(&mut l, ChannelId::new(1), true);
+        toggle_channel(&mut l, ChannelId::new(2), true);
+        assert_eq!(l, [1, 2]);
+        toggle_channel(&mut l, ChannelId::new(1), false);
+        toggle_channel(&mut l, ChannelId::new(5), false);
+        assert_eq!(l, [2]);
+    }
+
+    #[test]
+    fn old_settings_files() {
+        // Guilds saved before the inline settings existed keep the watcher on
+        let s: GuildSettings = ron::de::from_str("(cleanup_on_delete: false)").unwrap();
+        assert!(!s.cleanup_on_delete);
+        assert!(s.inline_enabled);
+        assert_eq!(s.inline_delimiters, INLINE_DELIMITERS);
+        assert!(s.inline_allow.is_empty() && s.inline_deny.is_empty());
+    }
+
+    #[test]
+    fn channel_ids() {
+        assert_eq!(channel_id("<#123456789>"), Some(ChannelId::new(123456789)));
+        assert_eq!(channel_id("123456789"), Some(ChannelId::new(123456789)));
+    }
+
+    #[test]
+    fn not_channel_ids() {
+        for s in ["", "#general", "<#123", "123>", "<@123>", "<#>", "0", "<#0>", "99999999999999999999999"] {
+            assert_eq!(channel_id(s), None, "`{}` parsed as a channel", s);
+        }
+    }
+}
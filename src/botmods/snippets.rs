@@ -0,0 +1,299 @@
+use regex::Regex;
+use serenity::{
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+        id::UserId,
+    },
+    builder::{
+        CreateEmbed,
+        CreateMessage,
+    },
+    prelude::*,
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group,
+        },
+        Args,
+    },
+};
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    sync::Arc,
+    pin::Pin,
+    fs::{
+        File,
+        write,
+    },
+};
+use futures::Future;
+use ron::{
+    de::from_reader,
+    ser::{
+        to_string_pretty,
+        PrettyConfig,
+    },
+};
+use crate::{
+    PREFIX,
+    CONFIG_DIR,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::{
+            parse_content,
+            render_snip,
+            MathText,
+        },
+        utils::BotModule,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_SNIPPETS: BotModule = BotModule {
+        command_group: &SNIPPETS_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}snip .*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Personal snippet library"]
+#[commands(snip)]
+struct Snippets;
+
+const MAX_SNIPPETS: usize = 100;
+const MAX_LISTING_LEN: usize = 4000;
+const SUBCOMMANDS: [&str; 3] = ["save", "list", "delete"];
+
+pub struct SnippetLibrary;
+
+impl TypeMapKey for SnippetLibrary {
+    type Value = Arc<RwLock<HashMap<u64, BTreeMap<String, MathText>>>>;
+}
+
+fn snippets_path() -> String {
+    format!("{}/snippets.ron", CONFIG_DIR.as_str())
+}
+
+pub fn load_snippets() -> HashMap<u64, BTreeMap<String, MathText>> {
+    let f = match File::open(snippets_path()) {
+        Ok(f) => f,
+        Err(_) => {return HashMap::new()}
+    };
+    match from_reader(f) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Failed parsing snippets file:\n{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn library_lock(ctx: &Context) -> Arc<RwLock<HashMap<u64, BTreeMap<String, MathText>>>> {
+    let data_read = ctx.data.read().await;
+    data_read.get::<SnippetLibrary>().expect("Oops!").clone() //TODO: Error handling
+}
+
+/// Saves a snippet for a user, under the next free `snipN` name if none is given
+pub async fn save_snippet(ctx: &Context, user: UserId, name: Option<&str>, text: MathText) -> Result<String, errors::Error> {
+    lazy_static! {
+        static ref NAME_RE: Regex = Regex::new(r"^[\w-]{1,32}$").unwrap();
+    }
+
+    let lock = library_lock(ctx).await;
+    let mut library = lock.write().await;
+    let snippets = library.entry(user.get()).or_default();
+
+    let name = match name {
+        Some(n) if !NAME_RE.is_match(n) || SUBCOMMANDS.contains(&n) => {
+            return Err(errors::Error::SnippetError(format!("`{}` can't be used as a name, use up to 32 letters, numbers, `-` or `_`", n)))
+        },
+        Some(n) => String::from(n),
+        None => (1..).map(|i| format!("snip{}", i)).find(|n| !snippets.contains_key(n)).unwrap(),
+    };
+
+    if !snippets.contains_key(&name) && snippets.len() >= MAX_SNIPPETS {
+        return Err(errors::Error::SnippetError(format!("You already have {} snippets saved, delete some first", MAX_SNIPPETS)))
+    }
+
+    snippets.insert(name.clone(), text);
+
+    write(snippets_path(), to_string_pretty(&*library, PrettyConfig::new())?)?;
+
+    Ok(name)
+}
+
+pub async fn get_snippet(ctx: &Context, user: UserId, name: &str) -> Option<MathText> {
+    let lock = library_lock(ctx).await;
+    let library = lock.read().await;
+    library.get(&user.get()).and_then(|s| s.get(name)).cloned()
+}
+
+/// Removes a user's snippet, returning whether there was one to remove
+pub async fn delete_snippet(ctx: &Context, user: UserId, name: &str) -> Result<bool, errors::Error> {
+    let lock = library_lock(ctx).await;
+    let mut library = lock.write().await;
+
+    let removed = match library.get_mut(&user.get()) {
+        Some(s) => s.remove(name).is_some(),
+        None => false,
+    };
+
+    if removed {
+        write(snippets_path(), to_string_pretty(&*library, PrettyConfig::new())?)?;
+    }
+
+    Ok(removed)
+}
+
+async fn list_msg(ctx: &Context, msg: &Message) -> CommandResult {
+    let listing = {
+        let lock = library_lock(ctx).await;
+        let library = lock.read().await;
+        match library.get(&msg.author.id.get()) {
+            Some(s) if !s.is_empty() => s.iter().map(|(n, t)| {
+                let mut src: String = t.as_str().chars().take(60).collect();
+                if src.len() < t.as_str().len() {
+                    src.push_str("...");
+                }
+                format!("`{}`: `{}`", n, src.replace('`', "'"))
+            }).collect::<Vec<String>>(),
+            _ => vec![format!("You have no saved snippets, save one with `{}snip save <name> <latex>`", PREFIX.as_str())],
+        }
+    };
+
+    // Leave room in the embed description for the overflow line
+    let mut shown = 0;
+    let mut description = String::new();
+    for l in &listing {
+        if description.len() + l.len() + 1 > MAX_LISTING_LEN {
+            break;
+        }
+        description.push_str(l);
+        description.push('\n');
+        shown += 1;
+    }
+    if shown < listing.len() {
+        description.push_str(&format!("...and {} more", listing.len() - shown));
+    }
+
+    msg.channel_id.send_message(&ctx.http, CreateMessage::new().embed(
+        CreateEmbed::new()
+            .title(format!("{}'s snippets", msg.author.name))
+            .description(description)
+    )).await?;
+    Ok(())
+}
+
+async fn recall(ctx: &Context, msg: &Message, name: &str) -> Result<(), errors::Error> {
+    match get_snippet(ctx, msg.author.id, name).await {
+        Some(t) => {
+            render_snip(ctx, msg, t).await?;
+        },
+        None => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SnippetError(format!("You have no snippet called `{}`", name))).await?;
+        },
+    }
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref RECALL_RE: Regex = Regex::new(format!(r"(?s)^{}snip (?P<name>\S+)\s*$", PREFIX.as_str()).as_str()).unwrap();
+    }
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    // Only recalling makes a render that can be replaced, the rest aren't worth redoing
+    if let Some(c) = RECALL_RE.captures(&inp_message.content) {
+        if !SUBCOMMANDS.contains(&&c["name"]) {
+            recall(&ctx, &inp_message, &c["name"]).await.ok();
+        }
+    }
+}
+
+#[command]
+#[description = "Save snippets and render them again later. Reply to a math message with `snip save <name>` to save its source"]
+#[usage = "save <name> [latex] | list | delete <name> | <name>"]
+#[example = "save gauss \\nabla \\cdot E = \\frac{\\rho}{\\epsilon_0}"]
+pub async fn snip(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let sub = match args.single::<String>() {
+        Ok(s) => s,
+        Err(_) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::ArgError(0, 1)).await?;
+            return Ok(())
+        }
+    };
+
+    match sub.as_str() {
+        "list" => list_msg(ctx, msg).await?,
+        "save" => {
+            let name = match args.single::<String>() {
+                Ok(n) => n,
+                Err(_) => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::ArgError(1, 2)).await?;
+                    return Ok(())
+                }
+            };
+
+            let text = match args.rest().trim() {
+                "" => msg.referenced_message.as_ref().and_then(|r| parse_content(&r.content)).map(|p| p.text),
+                src => Some(MathText::Latex(String::from(src))),
+            };
+
+            let text = match text {
+                Some(t) => t,
+                None => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SnippetError(String::from("Give the LaTeX to save, or reply to a math message"))).await?;
+                    return Ok(())
+                }
+            };
+
+            match save_snippet(ctx, msg.author.id, Some(&name), text).await {
+                Ok(n) => {
+                    msg.reply(&ctx.http, format!("Saved `{}`, render it with `{}snip {}`", n, PREFIX.as_str(), n)).await?;
+                },
+                Err(e) => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+                },
+            }
+        },
+        "delete" => {
+            let name = args.rest().trim();
+            match delete_snippet(ctx, msg.author.id, name).await {
+                Ok(true) => {
+                    msg.reply(&ctx.http, format!("Deleted `{}`", name)).await?;
+                },
+                Ok(false) => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &errors::Error::SnippetError(format!("You have no snippet called `{}`", name))).await?;
+                },
+                Err(e) => {
+                    err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+                },
+            }
+        },
+        name => recall(ctx, msg, name).await?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,519 @@
+use regex::Regex;
+use serenity::{
+    async_trait,
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    builder::{
+        CreateAttachment,
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+        CreateMessage,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+        application::Interaction,
+        id::{ChannelId, MessageId},
+        user::User,
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use tiny_skia::Color;
+use crate::{
+    PREFIX,
+    Interactables,
+    Editables,
+    botmods::{
+        errors,
+        errors::err_msg,
+        unicode::unicode_to_latex,
+        utils::{
+            delete_responses,
+            loading_msg,
+            output_target,
+            OutputLevel,
+            Buttons,
+            BotModule,
+            Editable,
+            Interactable,
+            push_to_editables,
+            push_to_interactables,
+        },
+    },
+    render::{
+        compile_svg,
+        encode_gif,
+        raster_pixmap,
+        rasterize,
+        MathText,
+        RenderTimings,
+        SCALE,
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_STEPS: BotModule = BotModule {
+        command_group: &STEPS_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}steps\s.*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Step by step derivations"]
+#[commands(steps)]
+struct Steps;
+
+const MAX_STEPS: usize = 20;
+const GIF_SCALE: u32 = 4;
+/// How long each frame of the GIF stays up, in hundredths of a second
+const GIF_DELAY: u16 = 150;
+const GIF_HOLD: u16 = 400;
+
+/// One step per line, or split at `\\` if it's all on one line
+pub fn parse_steps(input: &str) -> Result<Vec<String>, errors::Error> {
+    let lines: Vec<&str> = input.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("```"))
+        .collect();
+
+    let steps: Vec<String> = if lines.len() == 1 {
+        lines[0].split(r"\\").map(|s| s.trim()).filter(|s| !s.is_empty()).map(String::from).collect()
+    } else {
+        // Lines copied out of an align environment still end in `\\`
+        lines.iter().map(|l| String::from(l.trim_end_matches(r"\\").trim_end())).collect()
+    };
+
+    if steps.is_empty() {
+        return Err(errors::Error::StepsError(String::from("Give the derivation one step per line")))
+    }
+    if steps.len() > MAX_STEPS {
+        return Err(errors::Error::StepsError(format!("That's {} steps, the limit is {}", steps.len(), MAX_STEPS)))
+    }
+
+    Ok(steps)
+}
+
+/// The first `shown` steps stacked up, lined up at their `&` if any step has one
+pub fn frame_latex(steps: &[String], shown: usize) -> String {
+    let env = if steps.iter().any(|s| s.contains('&')) {"aligned"} else {"gathered"};
+    format!("$\\displaystyle \\begin{{{}}} {} \\end{{{}}}$", env, steps[..shown].join(r" \\ "), env)
+}
+
+#[derive(Clone)]
+pub struct Walkthrough {
+    steps: Vec<String>,
+    shown: usize,
+    /// Compiled frames, by the number of steps they show minus one
+    frames: Vec<Option<Vec<u8>>>,
+    inp_message: Message,
+    message: Option<Message>,
+    /// GIFs posted for the derivation, they go when it does
+    files: Vec<Message>,
+}
+
+impl Walkthrough {
+    pub fn new(steps: Vec<String>, inp_message: &Message) -> Walkthrough {
+        let steps: Vec<String> = steps.iter().map(|s| unicode_to_latex(s)).collect();
+        Walkthrough {
+            frames: vec![None; steps.len()],
+            steps,
+            shown: 1,
+            inp_message: inp_message.clone(),
+            message: None,
+            files: vec![],
+        }
+    }
+
+    /// The SVG of the frame showing the first `shown` steps, compiled once and kept for the GIF
+    async fn frame_svg(&mut self, shown: usize) -> Result<Vec<u8>, errors::Error> {
+        if let Some(f) = &self.frames[shown - 1] {
+            return Ok(f.clone())
+        }
+
+        let svg = compile_svg(&MathText::Latex(frame_latex(&self.steps, shown)), &mut RenderTimings::default()).await?;
+
+        self.frames[shown - 1] = Some(svg.clone());
+        Ok(svg)
+    }
+
+    async fn frame(&mut self) -> Result<Vec<u8>, errors::Error> {
+        let svg = self.frame_svg(self.shown).await?;
+        Ok(rasterize(&svg, SCALE, &mut RenderTimings::default())?)
+    }
+
+    /// Every frame in a loop, holding on the full derivation at the end
+    pub async fn gif(&mut self) -> Result<Vec<u8>, errors::Error> {
+        let mut frames = vec![];
+        for shown in 1..=self.steps.len() {
+            let svg = self.frame_svg(shown).await?;
+            let delay = if shown == self.steps.len() {GIF_HOLD} else {GIF_DELAY};
+            frames.push((raster_pixmap(&svg, GIF_SCALE, Color::BLACK)?, delay));
+        }
+
+        Ok(encode_gif(&frames)?)
+    }
+
+    async fn gif_msg(&mut self, ctx: &Context, for_user: &User) -> Result<Message, errors::Error> {
+        let gif = self.gif().await?;
+
+        let (c_id, _) = output_target(ctx, &self.inp_message.channel_id, for_user).await?;
+        let m = c_id.send_message(&ctx.http, CreateMessage::new()
+            .content("The derivation above, step by step")
+            .add_file(CreateAttachment::bytes(gif, "steps.gif"))
+        ).await?;
+
+        Ok(m)
+    }
+
+    /// The steps shown so far as text, for where images aren't allowed
+    fn listing(&self) -> String {
+        self.steps[..self.shown].iter().enumerate()
+            .map(|(i, s)| format!("{}. `{}`", i + 1, s))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Renders the current frame, sending it in place of `old` if given
+    async fn send(&mut self, ctx: &Context, old: Option<&Message>) -> Result<(), errors::Error> {
+        let (c_id, level) = output_target(ctx, &self.inp_message.channel_id, &self.inp_message.author).await?;
+
+        let image = if level >= OutputLevel::File {
+            match self.frame().await {
+                Ok(i) => Some(i),
+                Err(e) => {
+                    self.message = Some(err_msg(ctx, &c_id, old, Some(&self.inp_message.author), &e).await?);
+                    return Ok(())
+                },
+            }
+        } else {
+            None
+        };
+
+        if let Some(m) = old {
+            m.delete(&ctx.http).await?;
+        }
+
+        let mut buttons = vec![Buttons::Delete];
+        if self.steps.len() > 1 {
+            buttons.extend(vec![Buttons::Prev, Buttons::Next, Buttons::ShowAll]);
+            if image.is_some() {
+                buttons.push(Buttons::Gif);
+            }
+        }
+
+        let title = format!("Step {} of {}", self.shown, self.steps.len());
+        let listing = self.listing();
+        let author = &self.inp_message.author;
+
+        let mut m = if level == OutputLevel::Embed {
+            let e = CreateEmbed::new().title(&title);
+            let e = if image.is_some() {
+                e.image("attachment://steps.png")
+            } else {
+                e.description(&listing)
+            };
+            CreateMessage::new().embed(e.footer(CreateEmbedFooter::new(format!("Requested by {}", author.tag())).icon_url(author.face())))
+        } else if image.is_some() {
+            CreateMessage::new().content(format!("**{}**", title))
+        } else {
+            CreateMessage::new().content(format!("**{}**\n{}", title, listing))
+        };
+        if let Some(i) = image {
+            m = m.add_file(CreateAttachment::bytes(i, "steps.png"));
+        }
+        self.message = Some(c_id.send_message(&ctx.http, m.components(Buttons::add_buttons(buttons))).await?);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Editable for Walkthrough {
+    async fn edit(&mut self, ctx: &Context) -> Result<(), errors::Error> {
+        lazy_static! {
+            static ref STEPS_RE: Regex = Regex::new(format!(r"(?s)^{}steps\s(?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        };
+
+        let old_m = self.message.clone();
+        let inp_message = self.inp_message.channel_id.message(&ctx, self.inp_message.id).await?;
+
+        let input = match STEPS_RE.captures(&inp_message.content) {
+            Some(c) => String::from(c.name("args").unwrap().as_str()),
+            None => {return Ok(())}
+        };
+
+        match parse_steps(&input) {
+            Ok(s) => {
+                let files = std::mem::take(&mut self.files);
+                *self = Walkthrough::new(s, &inp_message);
+                self.files = files;
+                self.send(ctx, old_m.as_ref()).await?;
+            },
+            Err(e) => {
+                self.inp_message = inp_message;
+                self.message = Some(err_msg(ctx, &self.inp_message.channel_id, old_m.as_ref(), Some(&self.inp_message.author), &e).await?);
+            },
+        }
+
+        let interactables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Interactables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut interactables = interactables_lock.write().await;
+            interactables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            'outer: for (p, i) in interactables.iter().enumerate() {
+                for j in i.get_response_message_id() {
+                    if let Some(m) = &old_m {
+                        if m.id == j {
+                            pos = Some(p);
+                            break 'outer;
+                        }
+                    }
+                }
+            }
+
+            if let Some(p) = pos {
+                interactables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        match &self.message {
+            Some(m) => vec![m.id.clone()],
+            None => vec![]
+        }
+    }
+
+    fn get_response_messages(&self) -> Vec<(ChannelId, MessageId)> {
+        self.message.iter().chain(self.files.iter()).map(|m| (m.channel_id, m.id)).collect()
+    }
+
+    fn get_input_message_id(&self) -> MessageId {
+        self.inp_message.id.clone()
+    }
+
+    fn get_command_pattern(&self) -> Regex {
+        MOD_STEPS.command_pattern[0].clone()
+    }
+}
+
+#[async_trait]
+impl Interactable for Walkthrough {
+    async fn interaction_respond(&mut self, ctx: &Context, interaction: Interaction) -> Result<(), errors::Error> {
+        let component_interaction = match interaction {
+            Interaction::Component(m) => m,
+            _ => {return Ok(())}
+        };
+
+        let button = Buttons::from(component_interaction.data.custom_id.as_str());
+
+        if button.author_only() && self.inp_message.author != component_interaction.user {
+            component_interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the person who posted the derivation can do that")
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
+        }
+
+        component_interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let old_m = match self.message.clone() {
+            Some(m) => m,
+            None => {return Ok(())}
+        };
+
+        match button {
+            Buttons::Delete => {
+                old_m.delete(&ctx).await?;
+                self.message = None;
+
+                let files: Vec<_> = self.files.drain(..).map(|m| (m.channel_id, m.id)).collect();
+                delete_responses(ctx, &files).await.ok();
+            },
+            Buttons::Next | Buttons::Prev | Buttons::ShowAll => {
+                let shown = match button {
+                    Buttons::Next => usize::min(self.shown + 1, self.steps.len()),
+                    Buttons::Prev => usize::max(self.shown - 1, 1),
+                    _ => self.steps.len(),
+                };
+
+                // Attachments can't be edited, so the frame is sent again
+                if shown != self.shown {
+                    self.shown = shown;
+                    self.send(ctx, Some(&old_m)).await?;
+                }
+            },
+            Buttons::Gif => {
+                match self.gif_msg(ctx, &component_interaction.user).await {
+                    Ok(m) => self.files.push(m),
+                    Err(e) => {err_msg(ctx, &self.inp_message.channel_id, None, Some(&component_interaction.user), &e).await?;},
+                }
+            },
+            _ => {}
+        }
+
+        let editables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut editables = editables_lock.write().await;
+            editables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            for (p, i) in editables.iter().enumerate() {
+                if self.inp_message.id == i.get_input_message_id() {
+                    pos = Some(p);
+                    break;
+                }
+            }
+
+            if let Some(p) = pos {
+                editables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        match &self.message {
+            Some(m) => vec![m.id.clone()],
+            None => vec![]
+        }
+    }
+}
+
+async fn run(ctx: &Context, msg: &Message, input: &str, old: Option<&Message>) -> Result<(), errors::Error> {
+    match parse_steps(input) {
+        Ok(s) => {
+            let mut w = Walkthrough::new(s, msg);
+            w.send(ctx, old).await?;
+
+            push_to_interactables(ctx, Box::new(w.clone())).await;
+            push_to_editables(ctx, Box::new(w.clone())).await;
+        },
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, old, Some(&msg.author), &e).await?;
+        },
+    }
+
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref STEPS_RE: Regex = Regex::new(format!(r"(?s)^{}steps\s(?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    };
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = STEPS_RE.captures(&inp_message.content) {
+        run(&ctx, &inp_message, c.name("args").unwrap().as_str(), None).await.ok();
+    }
+}
+
+#[command]
+#[description = "Post a derivation one step at a time, one step per line. Lines can be lined up with `&` like in `align`"]
+#[usage = "<steps, one per line>"]
+#[example = "(a+b)^2 &= (a+b)(a+b)\n&= a^2 + ab + ba + b^2\n&= a^2 + 2ab + b^2"]
+pub async fn steps(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    let lm = loading_msg(ctx, &msg.channel_id, &msg.author).await?;
+    run(ctx, msg, input, Some(&lm)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(input: &str) -> Vec<String> {
+        parse_steps(input).unwrap()
+    }
+
+    #[test]
+    fn one_step_per_line() {
+        assert_eq!(steps("a &= b\n\n  &= c  \n"), vec!["a &= b", "&= c"]);
+    }
+
+    #[test]
+    fn align_line_endings() {
+        assert_eq!(steps("a &= b \\\\\n&= c \\\\"), vec!["a &= b", "&= c"]);
+    }
+
+    #[test]
+    fn single_line_splits() {
+        assert_eq!(steps(r"x+1=2 \\ x=1 \\"), vec!["x+1=2", "x=1"]);
+    }
+
+    #[test]
+    fn code_fences() {
+        assert_eq!(steps("```latex\na = b\n= c\n```"), vec!["a = b", "= c"]);
+    }
+
+    #[test]
+    fn step_limits() {
+        assert!(matches!(parse_steps("  \n```\n```"), Err(errors::Error::StepsError(_))));
+
+        let too_many: Vec<String> = (0..=MAX_STEPS).map(|i| format!("x = {}", i)).collect();
+        assert!(matches!(parse_steps(&too_many.join("\n")), Err(errors::Error::StepsError(_))));
+        assert_eq!(parse_steps(&too_many[1..].join("\n")).unwrap().len(), MAX_STEPS);
+    }
+
+    #[test]
+    fn frames() {
+        let gathered = vec![String::from("a = b"), String::from("b = c")];
+        assert_eq!(frame_latex(&gathered, 1), r"$\displaystyle \begin{gathered} a = b \end{gathered}$");
+        assert_eq!(frame_latex(&gathered, 2), r"$\displaystyle \begin{gathered} a = b \\ b = c \end{gathered}$");
+
+        // One `&` anywhere lines them all up, even before that step is shown
+        let aligned = vec![String::from("a = b"), String::from("&= c")];
+        assert_eq!(frame_latex(&aligned, 1), r"$\displaystyle \begin{aligned} a = b \end{aligned}$");
+    }
+}
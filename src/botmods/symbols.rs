@@ -0,0 +1,769 @@
+use regex::Regex;
+use serenity::{
+    async_trait,
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    builder::{
+        CreateAttachment,
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+        CreateMessage,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+        application::Interaction,
+        id::{ChannelId, MessageId},
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use crate::{
+    PREFIX,
+    Interactables,
+    Editables,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::{
+            MathSnip,
+        },
+        utils::{
+            loading_msg,
+            output_target,
+            OutputLevel,
+            Buttons,
+            BotModule,
+            Editable,
+            Interactable,
+            push_to_editables,
+            push_to_interactables,
+        },
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_SYMBOLS: BotModule = BotModule {
+        command_group: &SYMBOLS_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}symbol .*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "LaTeX symbol lookup"]
+#[commands(symbol)]
+struct Symbols;
+
+const PAGE_SIZE: usize = 12;
+const COLUMNS: usize = 3;
+
+/// Command, Unicode character, package and name of every symbol the lookup knows about
+const SYMBOLS: &[(&str, &str, &str, &str)] = &[
+    // Greek letters
+    (r"\alpha", "α", "latex", "alpha"), (r"\beta", "β", "latex", "beta"),
+    (r"\gamma", "γ", "latex", "gamma"), (r"\delta", "δ", "latex", "delta"),
+    (r"\epsilon", "ϵ", "latex", "epsilon"), (r"\varepsilon", "ε", "latex", "variant epsilon"),
+    (r"\zeta", "ζ", "latex", "zeta"), (r"\eta", "η", "latex", "eta"),
+    (r"\theta", "θ", "latex", "theta"), (r"\vartheta", "ϑ", "latex", "variant theta"),
+    (r"\iota", "ι", "latex", "iota"), (r"\kappa", "κ", "latex", "kappa"),
+    (r"\lambda", "λ", "latex", "lambda"), (r"\mu", "μ", "latex", "mu"),
+    (r"\nu", "ν", "latex", "nu"), (r"\xi", "ξ", "latex", "xi"),
+    (r"\pi", "π", "latex", "pi"), (r"\varpi", "ϖ", "latex", "variant pi"),
+    (r"\rho", "ρ", "latex", "rho"), (r"\varrho", "ϱ", "latex", "variant rho"),
+    (r"\sigma", "σ", "latex", "sigma"), (r"\varsigma", "ς", "latex", "final sigma"),
+    (r"\tau", "τ", "latex", "tau"), (r"\upsilon", "υ", "latex", "upsilon"),
+    (r"\phi", "ϕ", "latex", "phi"), (r"\varphi", "φ", "latex", "variant phi"),
+    (r"\chi", "χ", "latex", "chi"), (r"\psi", "ψ", "latex", "psi"),
+    (r"\omega", "ω", "latex", "omega"), (r"\Gamma", "Γ", "latex", "capital gamma"),
+    (r"\Delta", "Δ", "latex", "capital delta"), (r"\Theta", "Θ", "latex", "capital theta"),
+    (r"\Lambda", "Λ", "latex", "capital lambda"), (r"\Xi", "Ξ", "latex", "capital xi"),
+    (r"\Pi", "Π", "latex", "capital pi"), (r"\Sigma", "Σ", "latex", "capital sigma"),
+    (r"\Upsilon", "Υ", "latex", "capital upsilon"), (r"\Phi", "Φ", "latex", "capital phi"),
+    (r"\Psi", "Ψ", "latex", "capital psi"), (r"\Omega", "Ω", "latex", "capital omega"),
+    (r"\digamma", "ϝ", "amssymb", "digamma"), (r"\varkappa", "ϰ", "amssymb", "variant kappa"),
+    // Binary operators
+    (r"\pm", "±", "latex", "plus minus"), (r"\mp", "∓", "latex", "minus plus"),
+    (r"\times", "×", "latex", "times multiplication cross"), (r"\div", "÷", "latex", "division"),
+    (r"\cdot", "⋅", "latex", "centered dot multiplication"), (r"\ast", "∗", "latex", "asterisk"),
+    (r"\star", "⋆", "latex", "star"), (r"\circ", "∘", "latex", "circle composition"),
+    (r"\bullet", "∙", "latex", "bullet"), (r"\oplus", "⊕", "latex", "circled plus direct sum xor"),
+    (r"\ominus", "⊖", "latex", "circled minus"), (r"\otimes", "⊗", "latex", "circled times tensor product"),
+    (r"\oslash", "⊘", "latex", "circled slash"), (r"\odot", "⊙", "latex", "circled dot"),
+    (r"\cap", "∩", "latex", "intersection cap"), (r"\cup", "∪", "latex", "union cup"),
+    (r"\sqcap", "⊓", "latex", "square cap meet"), (r"\sqcup", "⊔", "latex", "square cup join"),
+    (r"\wedge", "∧", "latex", "wedge logical and"), (r"\vee", "∨", "latex", "vee logical or"),
+    (r"\setminus", "∖", "latex", "set minus difference"), (r"\wr", "≀", "latex", "wreath product"),
+    (r"\dagger", "†", "latex", "dagger adjoint"), (r"\ddagger", "‡", "latex", "double dagger"),
+    (r"\amalg", "⨿", "latex", "amalgamation coproduct"), (r"\uplus", "⊎", "latex", "multiset union"),
+    (r"\diamond", "⋄", "latex", "diamond"), (r"\triangleleft", "◁", "latex", "triangle left normal subgroup"),
+    (r"\triangleright", "▷", "latex", "triangle right"), (r"\boxplus", "⊞", "amssymb", "boxed plus"),
+    (r"\boxminus", "⊟", "amssymb", "boxed minus"), (r"\boxtimes", "⊠", "amssymb", "boxed times"),
+    (r"\boxdot", "⊡", "amssymb", "boxed dot"), (r"\ltimes", "⋉", "amssymb", "left semidirect product"),
+    (r"\rtimes", "⋊", "amssymb", "right semidirect product"), (r"\dotplus", "∔", "amssymb", "dot plus"),
+    // Relations
+    (r"\leq", "≤", "latex", "less than or equal"), (r"\geq", "≥", "latex", "greater than or equal"),
+    (r"\neq", "≠", "latex", "not equal"), (r"\equiv", "≡", "latex", "equivalent congruent identical"),
+    (r"\approx", "≈", "latex", "approximately equal"), (r"\sim", "∼", "latex", "similar tilde"),
+    (r"\simeq", "≃", "latex", "similar or equal homotopic"), (r"\cong", "≅", "latex", "congruent isomorphic"),
+    (r"\propto", "∝", "latex", "proportional to"), (r"\ll", "≪", "latex", "much less than"),
+    (r"\gg", "≫", "latex", "much greater than"), (r"\subset", "⊂", "latex", "subset"),
+    (r"\supset", "⊃", "latex", "superset"), (r"\subseteq", "⊆", "latex", "subset or equal"),
+    (r"\supseteq", "⊇", "latex", "superset or equal"), (r"\in", "∈", "latex", "element of in"),
+    (r"\ni", "∋", "latex", "contains as member"), (r"\notin", "∉", "latex", "not element of"),
+    (r"\perp", "⊥", "latex", "perpendicular orthogonal"), (r"\parallel", "∥", "latex", "parallel"),
+    (r"\mid", "∣", "latex", "divides such that"), (r"\models", "⊨", "latex", "models satisfies"),
+    (r"\vdash", "⊢", "latex", "proves turnstile"), (r"\dashv", "⊣", "latex", "left turnstile adjoint"),
+    (r"\prec", "≺", "latex", "precedes"), (r"\succ", "≻", "latex", "succeeds"),
+    (r"\preceq", "⪯", "latex", "precedes or equal"), (r"\succeq", "⪰", "latex", "succeeds or equal"),
+    (r"\asymp", "≍", "latex", "asymptotically equal"), (r"\doteq", "≐", "latex", "dot equal approaches"),
+    (r"\sqsubseteq", "⊑", "latex", "square subset or equal"), (r"\sqsupseteq", "⊒", "latex", "square superset or equal"),
+    (r"\bowtie", "⋈", "latex", "bowtie natural join"), (r"\leqslant", "⩽", "amssymb", "slanted less than or equal"),
+    (r"\geqslant", "⩾", "amssymb", "slanted greater than or equal"), (r"\lesssim", "≲", "amssymb", "less than or similar"),
+    (r"\gtrsim", "≳", "amssymb", "greater than or similar"), (r"\nleq", "≰", "amssymb", "not less than or equal"),
+    (r"\ngeq", "≱", "amssymb", "not greater than or equal"), (r"\subsetneq", "⊊", "amssymb", "proper subset not equal"),
+    (r"\supsetneq", "⊋", "amssymb", "proper superset not equal"), (r"\approxeq", "≊", "amssymb", "approximately equal or equal"),
+    (r"\triangleq", "≜", "amssymb", "defined as delta equal"), (r"\coloneqq", "≔", "mathtools", "colon equals defined as assignment"),
+    (r"\nmid", "∤", "amssymb", "does not divide"), (r"\vDash", "⊨", "amssymb", "double turnstile true"),
+    (r"\therefore", "∴", "amssymb", "therefore"), (r"\because", "∵", "amssymb", "because"),
+    // Arrows
+    (r"\leftarrow", "←", "latex", "left arrow"), (r"\rightarrow", "→", "latex", "right arrow"),
+    (r"\to", "→", "latex", "to right arrow maps"), (r"\gets", "←", "latex", "gets left arrow"),
+    (r"\leftrightarrow", "↔", "latex", "left right arrow"), (r"\Leftarrow", "⇐", "latex", "double left arrow implied by"),
+    (r"\Rightarrow", "⇒", "latex", "double right arrow implies"), (r"\Leftrightarrow", "⇔", "latex", "double left right arrow if and only if"),
+    (r"\longrightarrow", "⟶", "latex", "long right arrow"), (r"\longleftarrow", "⟵", "latex", "long left arrow"),
+    (r"\Longrightarrow", "⟹", "latex", "long double right arrow"), (r"\iff", "⟺", "latex", "if and only if iff"),
+    (r"\implies", "⟹", "amsmath", "implies"), (r"\impliedby", "⟸", "amsmath", "implied by"),
+    (r"\mapsto", "↦", "latex", "maps to"), (r"\longmapsto", "⟼", "latex", "long maps to"),
+    (r"\hookrightarrow", "↪", "latex", "hook right arrow injection inclusion"), (r"\hookleftarrow", "↩", "latex", "hook left arrow"),
+    (r"\uparrow", "↑", "latex", "up arrow"), (r"\downarrow", "↓", "latex", "down arrow"),
+    (r"\updownarrow", "↕", "latex", "up down arrow"), (r"\Uparrow", "⇑", "latex", "double up arrow"),
+    (r"\Downarrow", "⇓", "latex", "double down arrow"), (r"\nearrow", "↗", "latex", "north east arrow"),
+    (r"\searrow", "↘", "latex", "south east arrow"), (r"\swarrow", "↙", "latex", "south west arrow"),
+    (r"\nwarrow", "↖", "latex", "north west arrow"), (r"\rightleftharpoons", "⇌", "latex", "equilibrium harpoons"),
+    (r"\leftharpoonup", "↼", "latex", "left harpoon up"), (r"\rightharpoonup", "⇀", "latex", "right harpoon up weak convergence"),
+    (r"\twoheadrightarrow", "↠", "amssymb", "two head right arrow surjection"), (r"\rightsquigarrow", "⇝", "amssymb", "squiggly right arrow leads to"),
+    (r"\circlearrowleft", "↺", "amssymb", "anticlockwise circle arrow"), (r"\circlearrowright", "↻", "amssymb", "clockwise circle arrow"),
+    (r"\xrightarrow{f}", "→", "amsmath", "extensible right arrow with label"),
+    // Big operators
+    (r"\sum", "∑", "latex", "sum sigma summation"), (r"\prod", "∏", "latex", "product pi"),
+    (r"\coprod", "∐", "latex", "coproduct"), (r"\int", "∫", "latex", "integral"),
+    (r"\iint", "∬", "amsmath", "double integral"), (r"\iiint", "∭", "amsmath", "triple integral"),
+    (r"\oint", "∮", "latex", "contour integral closed"), (r"\bigcup", "⋃", "latex", "big union"),
+    (r"\bigcap", "⋂", "latex", "big intersection"), (r"\bigoplus", "⨁", "latex", "big circled plus direct sum"),
+    (r"\bigotimes", "⨂", "latex", "big circled times tensor product"), (r"\bigvee", "⋁", "latex", "big vee or"),
+    (r"\bigwedge", "⋀", "latex", "big wedge and"), (r"\bigsqcup", "⨆", "latex", "big square cup"),
+    (r"\biguplus", "⨄", "latex", "big multiset union"),
+    // Delimiters
+    (r"\langle", "⟨", "latex", "left angle bracket"), (r"\rangle", "⟩", "latex", "right angle bracket"),
+    (r"\lfloor", "⌊", "latex", "left floor"), (r"\rfloor", "⌋", "latex", "right floor"),
+    (r"\lceil", "⌈", "latex", "left ceiling"), (r"\rceil", "⌉", "latex", "right ceiling"),
+    (r"\lvert", "|", "amsmath", "left vertical bar absolute value"), (r"\rvert", "|", "amsmath", "right vertical bar"),
+    (r"\lVert", "‖", "amsmath", "left double bar norm"), (r"\rVert", "‖", "amsmath", "right double bar norm"),
+    // Miscellaneous
+    (r"\infty", "∞", "latex", "infinity"), (r"\partial", "∂", "latex", "partial derivative"),
+    (r"\nabla", "∇", "latex", "nabla del gradient"), (r"\forall", "∀", "latex", "for all"),
+    (r"\exists", "∃", "latex", "there exists"), (r"\nexists", "∄", "amssymb", "there does not exist"),
+    (r"\emptyset", "∅", "latex", "empty set"), (r"\varnothing", "∅", "amssymb", "variant empty set"),
+    (r"\neg", "¬", "latex", "logical not negation"), (r"\top", "⊤", "latex", "top true"),
+    (r"\bot", "⊥", "latex", "bottom false"), (r"\angle", "∠", "latex", "angle"),
+    (r"\measuredangle", "∡", "amssymb", "measured angle"), (r"\triangle", "△", "latex", "triangle"),
+    (r"\square", "□", "amssymb", "square box"), (r"\blacksquare", "■", "amssymb", "black square end of proof qed"),
+    (r"\hbar", "ℏ", "latex", "h bar reduced planck"), (r"\hslash", "ℏ", "amssymb", "h slash"),
+    (r"\ell", "ℓ", "latex", "script small l ell"), (r"\wp", "℘", "latex", "weierstrass p"),
+    (r"\Re", "ℜ", "latex", "real part"), (r"\Im", "ℑ", "latex", "imaginary part"),
+    (r"\aleph", "ℵ", "latex", "aleph cardinal"), (r"\beth", "ℶ", "amssymb", "beth"),
+    (r"\imath", "ı", "latex", "dotless i"), (r"\jmath", "ȷ", "latex", "dotless j"),
+    (r"\prime", "′", "latex", "prime"), (r"\backprime", "‵", "amssymb", "back prime"),
+    (r"\surd", "√", "latex", "surd root"), (r"\flat", "♭", "latex", "flat"),
+    (r"\natural", "♮", "latex", "natural"), (r"\sharp", "♯", "latex", "sharp"),
+    (r"\clubsuit", "♣", "latex", "club suit"), (r"\diamondsuit", "♢", "latex", "diamond suit"),
+    (r"\heartsuit", "♡", "latex", "heart suit"), (r"\spadesuit", "♠", "latex", "spade suit"),
+    (r"\checkmark", "✓", "amssymb", "check mark tick"), (r"\ldots", "…", "latex", "low dots ellipsis"),
+    (r"\cdots", "⋯", "latex", "centered dots ellipsis"), (r"\vdots", "⋮", "latex", "vertical dots"),
+    (r"\ddots", "⋱", "latex", "diagonal dots"), (r"^\circ", "°", "latex", "degree"),
+    // Accents and alphabets
+    (r"\hat{a}", "â", "latex", "hat circumflex"), (r"\widehat{ab}", "â", "latex", "wide hat"),
+    (r"\bar{a}", "ā", "latex", "bar macron"), (r"\overline{ab}", "a̅", "latex", "overline conjugate closure"),
+    (r"\underline{ab}", "a̲", "latex", "underline"), (r"\vec{a}", "a⃗", "latex", "vector arrow"),
+    (r"\dot{a}", "ȧ", "latex", "dot time derivative"), (r"\ddot{a}", "ä", "latex", "double dot second derivative"),
+    (r"\tilde{a}", "ã", "latex", "tilde"), (r"\widetilde{ab}", "ã", "latex", "wide tilde"),
+    (r"\mathbb{R}", "ℝ", "amssymb", "blackboard bold double struck real numbers"), (r"\mathbb{N}", "ℕ", "amssymb", "blackboard bold natural numbers"),
+    (r"\mathbb{Z}", "ℤ", "amssymb", "blackboard bold integers"), (r"\mathbb{Q}", "ℚ", "amssymb", "blackboard bold rationals"),
+    (r"\mathbb{C}", "ℂ", "amssymb", "blackboard bold complex numbers"), (r"\mathcal{L}", "ℒ", "latex", "calligraphic capital L"),
+    (r"\mathscr{L}", "ℒ", "mathrsfs", "script capital L lagrangian"), (r"\mathcal{F}", "ℱ", "latex", "calligraphic capital F"),
+    (r"\mathscr{F}", "ℱ", "mathrsfs", "script capital F"), (r"\mathfrak{g}", "𝔤", "amssymb", "fraktur g lie algebra"),
+    (r"\mathbf{v}", "𝐯", "latex", "bold vector"), (r"\boldsymbol{\alpha}", "𝛂", "amsmath", "bold symbol greek"),
+    (r"\mathrm{d}", "d", "latex", "upright roman d differential"),
+    // physics and mhchem
+    (r"\dv{f}{x}", "ⅆ", "physics", "derivative"), (r"\pdv{f}{x}", "∂", "physics", "partial derivative"),
+    (r"\abs{x}", "|", "physics", "absolute value"), (r"\norm{x}", "‖", "physics", "norm"),
+    (r"\ket{\psi}", "⟩", "physics", "ket dirac"), (r"\bra{\psi}", "⟨", "physics", "bra dirac"),
+    (r"\braket{a}{b}", "⟨", "physics", "braket inner product dirac"), (r"\ce{H2O}", "", "mhchem", "chemical formula"),
+];
+
+/// Indices into `SYMBOLS` matching a name, command, character or package, best matches first
+pub fn search(query: &str) -> Vec<usize> {
+    let query = query.trim();
+    if query.is_empty() {
+        return vec![]
+    }
+
+    let q_cmd = query.trim_start_matches('\\');
+    let q_lower = q_cmd.to_lowercase();
+    let q_words: Vec<&str> = q_lower.split_whitespace().collect();
+
+    let mut found: Vec<(u8, usize)> = SYMBOLS.iter().enumerate().filter_map(|(i, (cmd, uni, pkg, name))| {
+        let c = cmd.trim_start_matches('\\').split('{').next().unwrap();
+        let name_words: Vec<String> = name.split_whitespace().map(|w| w.to_lowercase()).collect();
+
+        if *uni == query || c == q_cmd {
+            Some((0, i))
+        } else if c.to_lowercase() == q_lower {
+            Some((1, i))
+        } else if c.to_lowercase().starts_with(&q_lower) {
+            Some((2, i))
+        } else if !q_words.is_empty() && q_words.iter().all(|q| name_words.iter().any(|w| w.starts_with(q))) {
+            Some((3, i))
+        } else if pkg.eq_ignore_ascii_case(query) {
+            Some((4, i))
+        } else {
+            None
+        }
+    }).collect();
+
+    found.sort();
+    found.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Commands that aren't symbols but get mistyped just as often
+const COMMANDS: &[&str] = &[
+    "frac", "dfrac", "tfrac", "sqrt", "binom", "sum", "prod", "int", "iint", "iiint", "oint", "lim", "limsup", "liminf",
+    "sup", "inf", "max", "min", "log", "ln", "exp", "sin", "cos", "tan", "sec", "csc", "cot", "arcsin", "arccos", "arctan",
+    "sinh", "cosh", "tanh", "det", "dim", "ker", "gcd", "deg", "arg", "Pr", "text", "textbf", "textit", "mathrm", "mathbf",
+    "mathit", "mathbb", "mathcal", "mathfrak", "mathscr", "mathsf", "boldsymbol", "operatorname", "left", "right", "middle",
+    "begin", "end", "overline", "underline", "overbrace", "underbrace", "overset", "underset", "stackrel", "hat", "widehat",
+    "bar", "tilde", "widetilde", "vec", "dot", "ddot", "quad", "qquad", "displaystyle", "boxed", "color", "pmod", "bmod",
+    "xrightarrow", "xleftarrow", "tag", "ce", "SI", "si", "dv", "pdv", "abs", "norm", "ket", "bra", "braket",
+];
+/// Misspellings further off than this aren't worth guessing at
+const MAX_DISTANCE: usize = 2;
+
+/// The command name out of a `SYMBOLS` entry, `\mathbb{R}` is `mathbb`
+fn command_name(cmd: &str) -> &str {
+    cmd.trim_start_matches('\\').split(|c: char| !c.is_ascii_alphabetic()).next().unwrap()
+}
+
+lazy_static! {
+    /// Every command name the bot knows of, without the backslash
+    static ref KNOWN_COMMANDS: Vec<&'static str> = {
+        let mut known: Vec<&str> = SYMBOLS.iter()
+            .map(|(cmd, _, _, _)| command_name(cmd))
+            .chain(COMMANDS.iter().cloned())
+            .filter(|c| !c.is_empty())
+            .collect();
+        known.sort_unstable();
+        known.dedup();
+        known
+    };
+}
+
+/// Edit distance where swapping two neighbouring letters counts as one edit, so `farc` is one away from `frac`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i-1] == b[j-1] {0} else {1};
+            d[i][j] = (d[i-1][j] + 1).min(d[i][j-1] + 1).min(d[i-1][j-1] + cost);
+            if i > 1 && j > 1 && a[i-1] == b[j-2] && a[i-2] == b[j-1] {
+                d[i][j] = d[i][j].min(d[i-2][j-2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Known commands close to one latex didn't know, closest first. Symbols are also found by what
+/// they're called, so `\intergal` turns up `\int`
+pub fn closest_commands(name: &str) -> Vec<&'static str> {
+    // Short names are one typo away from too many things
+    let max = if name.chars().count() <= 3 {1} else {MAX_DISTANCE};
+    let lower = name.to_lowercase();
+
+    let by_command = KNOWN_COMMANDS.iter().map(|c| (edit_distance(name, c), 0, *c));
+    // Going by the name is more of a guess, so it ranks behind a command just as close,
+    // and plain "integral" goes before "double integral"
+    let by_name = SYMBOLS.iter().filter_map(|(cmd, _, _, desc)| {
+        let d = desc.split_whitespace().filter(|w| w.len() > 3).map(|w| edit_distance(&lower, w)).min()?;
+        Some((d + 1, desc.split_whitespace().count(), command_name(cmd)))
+    });
+
+    let mut close: Vec<(usize, bool, usize, &str)> = by_command.chain(by_name)
+        .filter(|(d, _, _)| *d <= max)
+        .map(|(d, words, c)| (d, !c.starts_with(name), words, c))
+        .collect();
+    close.sort();
+
+    let mut found: Vec<&str> = vec![];
+    for (_, _, _, c) in close {
+        if !found.contains(&c) && c != name {
+            found.push(c);
+        }
+    }
+    found.truncate(3);
+    found
+}
+
+/// Swaps every `\from` in some LaTeX for `\to`, leaving alone longer commands that start the same way
+pub fn replace_command(latex: &str, from: &str, to: &str) -> String {
+    let pattern = format!("\\{}", from);
+    let mut out = String::new();
+    let mut rest = latex;
+
+    while let Some(i) = rest.find(&pattern) {
+        let after = &rest[i + pattern.len()..];
+        out.push_str(&rest[..i]);
+        if after.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            out.push_str(&pattern);
+        } else {
+            out.push('\\');
+            out.push_str(to);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escapes a command so it shows up literally in `\texttt`
+fn tt_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '{' | '}' | '_' | '&' | '#' | '%' | '$' => {
+                out.push('\\');
+                out.push(c);
+            },
+            '^' => out.push_str(r"\^{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A table of rendered symbols next to their commands
+fn grid_latex(symbols: &[usize]) -> String {
+    let cells: Vec<String> = symbols.iter().map(|i| {
+        let cmd = SYMBOLS[*i].0;
+        format!("${}$ & \\texttt{{{}}}", cmd, tt_escape(cmd))
+    }).collect();
+
+    let rows: Vec<String> = cells.chunks(COLUMNS).map(|r| r.join(" & ")).collect();
+
+    format!("\\begin{{tabular}}{{{}}} {} \\end{{tabular}}", "cl".repeat(COLUMNS), rows.join(r" \\ "))
+}
+
+#[derive(Clone)]
+pub struct SymbolSearch {
+    query: String,
+    matches: Vec<usize>,
+    page: usize,
+    inp_message: Message,
+    message: Option<Message>,
+}
+
+impl SymbolSearch {
+    pub fn new(query: &str, inp_message: &Message) -> SymbolSearch {
+        SymbolSearch {
+            query: String::from(query.trim()),
+            matches: search(query),
+            page: 0,
+            inp_message: inp_message.clone(),
+            message: None,
+        }
+    }
+
+    fn pages(&self) -> usize {
+        (self.matches.len() + PAGE_SIZE - 1) / PAGE_SIZE
+    }
+
+    fn page_matches(&self) -> &[usize] {
+        let start = self.page * PAGE_SIZE;
+        let end = usize::min(start + PAGE_SIZE, self.matches.len());
+        &self.matches[start..end]
+    }
+
+    /// One line per symbol, so the commands can be copied
+    fn listing(&self) -> String {
+        self.page_matches().iter().map(|i| {
+            let (cmd, uni, pkg, name) = SYMBOLS[*i];
+            let pkg = if pkg == "latex" {
+                String::new()
+            } else {
+                format!(" (`\\usepackage{{{}}}`)", pkg)
+            };
+            format!("`{}` {} {}{}", cmd, uni, name, pkg)
+        }).collect::<Vec<String>>().join("\n")
+    }
+
+    /// Renders the current page, sending it in place of `old` if given
+    async fn send(&mut self, ctx: &Context, old: Option<&Message>) -> Result<(), errors::Error> {
+        let (c_id, level) = output_target(ctx, &self.inp_message.channel_id, &self.inp_message.author).await?;
+
+        if self.matches.is_empty() {
+            if let Some(m) = old {
+                m.delete(&ctx.http).await?;
+            }
+            err_msg(ctx, &c_id, None, Some(&self.inp_message.author), &errors::Error::SymbolError(format!("No symbols found for `{}`", self.query))).await?;
+            self.message = None;
+            return Ok(())
+        }
+
+        let image = if level >= OutputLevel::File {
+            let mut snip = MathSnip::generated(grid_latex(self.page_matches()), &self.inp_message).await;
+            // No picture is still a useful answer, the listing has everything
+            match snip.cmpl().await {
+                Ok(_) => snip.image().cloned(),
+                Err(_) => None,
+            }
+        } else {
+            None
+        };
+
+        if let Some(m) = old {
+            m.delete(&ctx.http).await?;
+        }
+
+        let mut buttons = vec![Buttons::Delete];
+        if self.pages() > 1 {
+            buttons.extend(vec![Buttons::Prev, Buttons::Next]);
+        }
+
+        let title = format!("Symbols matching `{}` ({}/{})", self.query, self.page + 1, self.pages());
+        let listing = self.listing();
+        let author = &self.inp_message.author;
+
+        let mut m = if level == OutputLevel::Embed {
+            let mut e = CreateEmbed::new()
+                .title(&title)
+                .description(&listing);
+            if image.is_some() {
+                e = e.image("attachment://symbols.png");
+            }
+            CreateMessage::new().embed(e.footer(CreateEmbedFooter::new(format!("Requested by {}", author.tag())).icon_url(author.face())))
+        } else {
+            CreateMessage::new().content(format!("**{}**\n{}", title, listing))
+        };
+        if let Some(i) = image {
+            m = m.add_file(CreateAttachment::bytes(i, "symbols.png"));
+        }
+        self.message = Some(c_id.send_message(&ctx.http, m.components(Buttons::add_buttons(buttons))).await?);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Editable for SymbolSearch {
+    async fn edit(&mut self, ctx: &Context) -> Result<(), errors::Error> {
+        lazy_static! {
+            static ref SYMBOL_RE: Regex = Regex::new(format!(r"(?s)^{}symbol (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        };
+
+        let old_m = self.message.clone();
+        let inp_message = self.inp_message.channel_id.message(&ctx, self.inp_message.id).await?;
+
+        let query = match SYMBOL_RE.captures(&inp_message.content) {
+            Some(c) => String::from(c.name("args").unwrap().as_str()),
+            None => {return Ok(())}
+        };
+
+        *self = SymbolSearch::new(&query, &inp_message);
+        self.send(ctx, old_m.as_ref()).await?;
+
+        let interactables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Interactables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut interactables = interactables_lock.write().await;
+            interactables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            'outer: for (p, i) in interactables.iter().enumerate() {
+                for j in i.get_response_message_id() {
+                    if let Some(m) = &old_m {
+                        if m.id == j {
+                            pos = Some(p);
+                            break 'outer;
+                        }
+                    }
+                }
+            }
+
+            if let Some(p) = pos {
+                interactables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        match &self.message {
+            Some(m) => vec![m.id.clone()],
+            None => vec![]
+        }
+    }
+
+    fn get_response_messages(&self) -> Vec<(ChannelId, MessageId)> {
+        self.message.iter().map(|m| (m.channel_id, m.id)).collect()
+    }
+
+    fn get_input_message_id(&self) -> MessageId {
+        self.inp_message.id.clone()
+    }
+
+    fn get_command_pattern(&self) -> Regex {
+        MOD_SYMBOLS.command_pattern[0].clone()
+    }
+}
+
+#[async_trait]
+impl Interactable for SymbolSearch {
+    async fn interaction_respond(&mut self, ctx: &Context, interaction: Interaction) -> Result<(), errors::Error> {
+        let component_interaction = match interaction {
+            Interaction::Component(m) => m,
+            _ => {return Ok(())}
+        };
+
+        let button = Buttons::from(component_interaction.data.custom_id.as_str());
+
+        if button.author_only() && self.inp_message.author != component_interaction.user {
+            component_interaction.create_response(ctx, CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("Only the person who searched can do that")
+                    .ephemeral(true)
+            )).await?;
+            return Ok(())
+        }
+
+        component_interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let old_m = match self.message.clone() {
+            Some(m) => m,
+            None => {return Ok(())}
+        };
+
+        match button {
+            Buttons::Delete => {
+                old_m.delete(&ctx).await?;
+                self.message = None;
+            },
+            Buttons::Next | Buttons::Prev => {
+                let pages = self.pages();
+                self.page = match button {
+                    Buttons::Next => (self.page + 1) % pages,
+                    _ => (self.page + pages - 1) % pages,
+                };
+
+                // Attachments can't be edited, so the page is sent again
+                self.send(ctx, Some(&old_m)).await?;
+            },
+            _ => {}
+        }
+
+        let editables_lock = {
+            let data_read = ctx.data.read().await;
+            data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
+        };
+
+        {
+            let mut editables = editables_lock.write().await;
+            editables.make_contiguous();
+
+            let mut pos: Option<usize> = None;
+
+            for (p, i) in editables.iter().enumerate() {
+                if self.inp_message.id == i.get_input_message_id() {
+                    pos = Some(p);
+                    break;
+                }
+            }
+
+            if let Some(p) = pos {
+                editables[p] = Box::new(self.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_response_message_id(&self) -> Vec<MessageId> {
+        match &self.message {
+            Some(m) => vec![m.id.clone()],
+            None => vec![]
+        }
+    }
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref SYMBOL_RE: Regex = Regex::new(format!(r"(?s)^{}symbol (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    };
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = SYMBOL_RE.captures(&inp_message.content) {
+        let mut s = SymbolSearch::new(c.name("args").unwrap().as_str(), &inp_message);
+        if s.send(&ctx, None).await.is_ok() {
+            push_to_interactables(&ctx, Box::new(s.clone())).await;
+            push_to_editables(&ctx, Box::new(s.clone())).await;
+        }
+    }
+}
+
+#[command]
+#[description = "Look up LaTeX symbols by name, command, Unicode character or package"]
+#[usage = "<name|character|package>"]
+#[example = "⊗"]
+pub async fn symbol(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let query = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    let lm = loading_msg(ctx, &msg.channel_id, &msg.author).await?;
+
+    let mut s = SymbolSearch::new(query, msg);
+    s.send(ctx, Some(&lm)).await?;
+
+    push_to_interactables(&ctx, Box::new(s.clone())).await;
+    push_to_editables(&ctx, Box::new(s.clone())).await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(query: &str) -> Vec<&'static str> {
+        search(query).into_iter().map(|i| SYMBOLS[i].0).collect()
+    }
+
+    #[test]
+    fn search_by_character() {
+        assert_eq!(commands("⊗")[0], r"\otimes");
+        assert_eq!(commands(" ≤ ")[0], r"\leq");
+    }
+
+    #[test]
+    fn search_by_command() {
+        assert_eq!(commands(r"\otimes")[0], r"\otimes");
+        assert_eq!(commands("otimes")[0], r"\otimes");
+        assert_eq!(commands("mathscr")[..2], [r"\mathscr{L}", r"\mathscr{F}"]);
+        assert!(commands("big").iter().all(|c| c.to_lowercase().starts_with(r"\big")));
+    }
+
+    #[test]
+    fn search_by_name() {
+        assert_eq!(commands("script L"), vec![r"\ell", r"\mathscr{L}"]);
+        assert!(commands("tensor").contains(&r"\otimes"));
+        assert!(commands("TENSOR").contains(&r"\bigotimes"));
+    }
+
+    #[test]
+    fn search_by_package() {
+        let mhchem = search("mhchem");
+        assert!(!mhchem.is_empty());
+        assert!(mhchem.iter().all(|i| SYMBOLS[*i].2 == "mhchem"));
+    }
+
+    #[test]
+    fn no_matches() {
+        assert!(search("").is_empty());
+        assert!(search("   ").is_empty());
+        assert!(search("zzzzzz").is_empty());
+    }
+
+    #[test]
+    fn exact_matches_first() {
+        let found = commands("in");
+        assert_eq!(found[0], r"\in");
+        assert!(found.contains(&r"\int"));
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("frac", "frac"), 0);
+        assert_eq!(edit_distance("farc", "frac"), 1);
+        assert_eq!(edit_distance("fra", "frac"), 1);
+        assert_eq!(edit_distance("alpah", "alpha"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(closest_commands("farc")[0], "frac");
+        assert_eq!(closest_commands("alpah")[0], "alpha");
+        assert_eq!(closest_commands("intergal")[0], "int");
+        assert!(closest_commands("frac").iter().all(|c| *c != "frac"));
+        assert!(closest_commands("qwertyuiop").is_empty());
+        assert!(closest_commands("farc").len() <= 3);
+    }
+
+    #[test]
+    fn replacing_commands() {
+        assert_eq!(replace_command(r"\farc{1}{2} + \farc12", "farc", "frac"), r"\frac{1}{2} + \frac12");
+        assert_eq!(replace_command(r"\in \int \in", "in", "ni"), r"\ni \int \ni");
+        assert_eq!(replace_command(r"\alpha", "beta", "gamma"), r"\alpha");
+    }
+
+    #[test]
+    fn grid() {
+        assert_eq!(tt_escape(r"\mathbb{R}_1^2"), r"\textbackslash{}mathbb\{R\}\_1\^{}2");
+
+        let grid = grid_latex(&search("otimes")[..1]);
+        assert_eq!(grid, r"\begin{tabular}{clclcl} $\otimes$ & \texttt{\textbackslash{}otimes} \end{tabular}");
+    }
+}
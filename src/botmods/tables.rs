@@ -0,0 +1,349 @@
+use regex::Regex;
+use serenity::{
+    framework::standard::{
+        CommandResult,
+        macros::{
+            command,
+            group
+        },
+        Args,
+    },
+    model::{
+        channel::Message,
+        event::MessageUpdateEvent,
+    },
+    prelude::*,
+};
+use std::pin::Pin;
+use futures::Future;
+use crate::{
+    PREFIX,
+    botmods::{
+        errors,
+        errors::err_msg,
+        markup::{
+            render_generated,
+        },
+        utils::{
+            strip_flags,
+            BotModule,
+        },
+    },
+};
+use lazy_static;
+
+lazy_static!(
+    pub static ref MOD_TABLES: BotModule = BotModule {
+        command_group: &TABLES_GROUP,
+        command_pattern: vec![
+            Regex::new(format!(r"(?s)^{}matrix .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}table .*$", PREFIX.as_str()).as_str()).unwrap(),
+        ],
+        editors: vec![
+            edit_handler_wrap,
+        ],
+        interactors: vec![],
+        watchers: vec![],
+    };
+);
+
+#[group]
+#[summary = "Matrix and table builders"]
+#[commands(matrix, table)]
+struct Tables;
+
+const MAX_CELLS: usize = 400;
+
+const MATRIX_FLAGS: [(&str, &str); 6] = [
+    ("--paren", "pmatrix"),
+    ("--bracket", "bmatrix"),
+    ("--brace", "Bmatrix"),
+    ("--vert", "vmatrix"),
+    ("--norm", "Vmatrix"),
+    ("--plain", "matrix"),
+];
+const TABLE_FLAGS: [&str; 1] = ["--noheader"];
+
+/// Removes a surrounding code block, which is the easy way to paste multi-line CSV
+fn strip_code_block(s: &str) -> &str {
+    let s = s.trim();
+    match s.strip_prefix("```").and_then(|s| s.strip_suffix("```")) {
+        // Drop a language tag like ```csv
+        Some(inner) => match inner.find('\n') {
+            Some(n) if !inner[..n].contains(',') && !inner[..n].contains(';') => &inner[n+1..],
+            _ => inner,
+        },
+        None => s,
+    }
+}
+
+/// Splits a CSV line, keeping delimiters inside "quoted fields"
+fn split_cells(line: &str, delim: char) -> Vec<String> {
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            c if c == delim && !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+
+    cells.into_iter().map(|c| String::from(c.trim())).collect()
+}
+
+/// Rows of cells from `a,b;c,d` on one line, or CSV with one row per line
+pub fn parse_grid(input: &str) -> Result<Vec<Vec<String>>, errors::Error> {
+    let input = strip_code_block(input);
+    let lines: Vec<&str> = input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+
+    let rows: Vec<Vec<String>> = if lines.len() > 1 {
+        let delim = if lines.iter().any(|l| l.contains('\t')) {
+            '\t'
+        } else if lines.iter().any(|l| l.contains(';')) {
+            ';'
+        } else {
+            ','
+        };
+        lines.iter().map(|l| split_cells(l, delim)).collect()
+    } else {
+        input.split(';').map(|r| r.trim()).filter(|r| !r.is_empty()).map(|r| split_cells(r, ',')).collect()
+    };
+
+    let cells: usize = rows.iter().map(|r| r.len()).sum();
+    if cells == 0 {
+        return Err(errors::Error::TableError(String::from("There's nothing in there")))
+    }
+    if cells > MAX_CELLS {
+        return Err(errors::Error::TableError(format!("That's {} cells, the limit is {}", cells, MAX_CELLS)))
+    }
+
+    Ok(rows)
+}
+
+/// A matrix environment from rows split by `;` and entries split by spaces or commas
+pub fn matrix_latex(input: &str, env: &str) -> Result<String, errors::Error> {
+    let rows: Vec<Vec<String>> = parse_grid(input)?.into_iter().map(|r| {
+        // `1 2 3` is a row of three, unless commas say otherwise
+        if r.len() == 1 {
+            r[0].split_whitespace().map(String::from).collect()
+        } else {
+            r
+        }
+    }).collect();
+
+    let cols = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let body: Vec<String> = rows.iter().map(|r| r.join(" & ")).collect();
+
+    // amsmath stops at 10 columns unless told otherwise
+    let setup = if cols > 10 {
+        format!("\\setcounter{{MaxMatrixCols}}{{{}}} ", cols)
+    } else {
+        String::new()
+    };
+
+    Ok(format!("{}$\\begin{{{}}} {} \\end{{{}}}$", setup, env, body.join(r" \\ "), env))
+}
+
+fn escape_text(s: &str) -> String {
+    // Cells with math in them are left for latex to deal with
+    if s.contains('$') {
+        return String::from(s)
+    }
+
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '&' | '%' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            },
+            '~' => out.push_str(r"\textasciitilde{}"),
+            '^' => out.push_str(r"\textasciicircum{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A ruled tabular, with the first row as a header unless told otherwise
+pub fn table_latex(input: &str, header: bool) -> Result<String, errors::Error> {
+    let rows = parse_grid(input)?;
+    let cols = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+
+    // Columns of numbers line up on the right
+    let body_rows = if header { &rows[1..] } else { &rows[..] };
+    let align: Vec<&str> = (0..cols).map(|c| {
+        let numeric = body_rows.iter().filter_map(|r| r.get(c)).filter(|v| !v.is_empty()).all(|v| v.parse::<f64>().is_ok());
+        let any = body_rows.iter().any(|r| r.get(c).map_or(false, |v| !v.is_empty()));
+        if numeric && any { "r" } else { "l" }
+    }).collect();
+
+    let lines: Vec<String> = rows.iter().enumerate().map(|(i, r)| {
+        let mut cells: Vec<String> = r.iter().map(|c| escape_text(c)).collect();
+        cells.resize(cols, String::new());
+        if header && i == 0 {
+            cells = cells.iter().map(|c| format!("\\textbf{{{}}}", c)).collect();
+            format!("{} \\\\ \\hline", cells.join(" & "))
+        } else {
+            format!("{} \\\\", cells.join(" & "))
+        }
+    }).collect();
+
+    Ok(format!("\\begin{{tabular}}{{|{}|}} \\hline {} \\hline \\end{{tabular}}", align.join("|"), lines.join(" ")))
+}
+
+async fn run(ctx: &Context, msg: &Message, cmd: &str, input: &str) -> Result<(), errors::Error> {
+    let latex = match cmd {
+        "matrix" => {
+            let flags: Vec<&str> = MATRIX_FLAGS.iter().map(|(f, _)| *f).collect();
+            let (found, input) = strip_flags(input, &flags);
+            let env = found.last()
+                .and_then(|f| MATRIX_FLAGS.iter().find(|(m, _)| m == f))
+                .map_or("pmatrix", |(_, e)| *e);
+            matrix_latex(input, env)
+        },
+        _ => {
+            let (found, input) = strip_flags(input, &TABLE_FLAGS);
+            table_latex(input, !found.iter().any(|f| f == "--noheader"))
+        },
+    };
+
+    match latex {
+        Ok(l) => {
+            render_generated(ctx, msg, l).await?;
+        },
+        Err(e) => {
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &e).await?;
+        },
+    }
+
+    Ok(())
+}
+
+fn edit_handler_wrap(ctx: Context, msg_upd_event: MessageUpdateEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(edit_handler(ctx, msg_upd_event))
+}
+
+async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
+    lazy_static! {
+        static ref TABLES_RE: Regex = Regex::new(format!(r"(?s)^{}(?P<cmd>matrix|table) (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+    }
+
+    let inp_message = match msg_upd_event.channel_id.message(&ctx, msg_upd_event.id).await {
+        Ok(m) => m,
+        Err(_) => {return},
+    };
+
+    if let Some(c) = TABLES_RE.captures(&inp_message.content) {
+        run(&ctx, &inp_message, &c["cmd"], &c["args"]).await.ok();
+    }
+}
+
+#[command]
+#[description = "Build a matrix, rows split by `;` and entries by spaces or commas"]
+#[usage = "[--paren|--bracket|--brace|--vert|--norm|--plain] <rows>"]
+#[example = "--bracket 1 2; 3 4"]
+pub async fn matrix(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    run(ctx, msg, "matrix", input).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Build a table from CSV, one row per line, or rows split by `;` on one line"]
+#[usage = "[--noheader] <csv>"]
+#[example = "Element, Mass; H, 1.008; He, 4.0026"]
+pub async fn table(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
+    let input = match arg.remains() {
+        Some(r) => Ok(r),
+        None => {
+            let err = errors::Error::ArgError(1, 0);
+            err_msg(ctx, &msg.channel_id, None, Some(&msg.author), &err).await?;
+            Err(err)
+        },
+    }?;
+
+    run(ctx, msg, "table", input).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(input: &str) -> Vec<Vec<String>> {
+        parse_grid(input).unwrap()
+    }
+
+    #[test]
+    fn one_line_grids() {
+        assert_eq!(grid("a, b; c, d"), vec![vec!["a", "b"], vec!["c", "d"]]);
+        assert_eq!(grid("1 2; 3 4;"), vec![vec!["1 2"], vec!["3 4"]]);
+    }
+
+    #[test]
+    fn csv_grids() {
+        assert_eq!(grid("```csv\nname,age\n\"Doe, J\",42\n```"), vec![vec!["name", "age"], vec!["Doe, J", "42"]]);
+        assert_eq!(grid("a;b\nc;d"), vec![vec!["a", "b"], vec!["c", "d"]]);
+        assert_eq!(grid("a\tb,c\nd\te"), vec![vec!["a", "b,c"], vec!["d", "e"]]);
+        assert_eq!(grid("\"say \"\"hi\"\"\",x\ny,z"), vec![vec!["say \"hi\"", "x"], vec!["y", "z"]]);
+    }
+
+    #[test]
+    fn grid_limits() {
+        assert!(matches!(parse_grid("  ;  ; "), Err(errors::Error::TableError(_))));
+        assert!(matches!(parse_grid("``````"), Err(errors::Error::TableError(_))));
+
+        let row = vec!["1"; MAX_CELLS].join(",");
+        assert_eq!(parse_grid(&row).unwrap()[0].len(), MAX_CELLS);
+        assert!(matches!(parse_grid(&format!("{},1", row)), Err(errors::Error::TableError(_))));
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!(matrix_latex("1 2; 3 4", "pmatrix").unwrap(), r"$\begin{pmatrix} 1 & 2 \\ 3 & 4 \end{pmatrix}$");
+        assert_eq!(matrix_latex("a, b c; d, e", "bmatrix").unwrap(), r"$\begin{bmatrix} a & b c \\ d & e \end{bmatrix}$");
+        assert_eq!(
+            matrix_latex(&(1..=11).map(|i| i.to_string()).collect::<Vec<String>>().join(" "), "matrix").unwrap(),
+            r"\setcounter{MaxMatrixCols}{11} $\begin{matrix} 1 & 2 & 3 & 4 & 5 & 6 & 7 & 8 & 9 & 10 & 11 \end{matrix}$"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            table_latex("item,cost\napples,1.5\npears,", true).unwrap(),
+            r"\begin{tabular}{|l|r|} \hline \textbf{item} & \textbf{cost} \\ \hline apples & 1.5 \\ pears &  \\ \hline \end{tabular}"
+        );
+        assert_eq!(
+            table_latex("1,x; 2", false).unwrap(),
+            r"\begin{tabular}{|r|l|} \hline 1 & x \\ 2 &  \\ \hline \end{tabular}"
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape_text(r"50% & #1_a {b} ~x^2 \o"), r"50\% \& \#1\_a \{b\} \textasciitilde{}x\textasciicircum{}2 \textbackslash{}o");
+        assert_eq!(escape_text("$x_1^2$"), "$x_1^2$");
+    }
+}
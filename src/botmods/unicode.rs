@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use lazy_static;
+
+lazy_static!{
+    static ref SYMBOLS: HashMap<&'static str, &'static str> = vec![
+        ("alpha", "α"), ("beta", "β"), ("gamma", "γ"), ("delta", "δ"), ("epsilon", "ϵ"),
+        ("varepsilon", "ε"), ("zeta", "ζ"), ("eta", "η"), ("theta", "θ"), ("vartheta", "ϑ"),
+        ("iota", "ι"), ("kappa", "κ"), ("lambda", "λ"), ("mu", "μ"), ("nu", "ν"), ("xi", "ξ"),
+        ("pi", "π"), ("varpi", "ϖ"), ("rho", "ρ"), ("varrho", "ϱ"), ("sigma", "σ"),
+        ("varsigma", "ς"), ("tau", "τ"), ("upsilon", "υ"), ("phi", "ϕ"), ("varphi", "φ"),
+        ("chi", "χ"), ("psi", "ψ"), ("omega", "ω"), ("Gamma", "Γ"), ("Delta", "Δ"),
+        ("Theta", "Θ"), ("Lambda", "Λ"), ("Xi", "Ξ"), ("Pi", "Π"), ("Sigma", "Σ"),
+        ("Upsilon", "Υ"), ("Phi", "Φ"), ("Psi", "Ψ"), ("Omega", "Ω"),
+        ("int", "∫"), ("iint", "∬"), ("iiint", "∭"), ("oint", "∮"), ("sum", "∑"), ("prod", "∏"),
+        ("infty", "∞"), ("partial", "∂"), ("nabla", "∇"), ("cdot", "·"), ("times", "×"),
+        ("div", "÷"), ("pm", "±"), ("mp", "∓"), ("leq", "≤"), ("le", "≤"), ("geq", "≥"),
+        ("ge", "≥"), ("neq", "≠"), ("ne", "≠"), ("approx", "≈"), ("equiv", "≡"), ("sim", "∼"),
+        ("simeq", "≃"), ("cong", "≅"), ("propto", "∝"), ("ll", "≪"), ("gg", "≫"),
+        ("to", "→"), ("rightarrow", "→"), ("leftarrow", "←"), ("leftrightarrow", "↔"),
+        ("Rightarrow", "⇒"), ("Leftarrow", "⇐"), ("implies", "⇒"), ("iff", "⇔"),
+        ("Leftrightarrow", "⇔"), ("mapsto", "↦"), ("uparrow", "↑"), ("downarrow", "↓"),
+        ("in", "∈"), ("notin", "∉"), ("ni", "∋"), ("subset", "⊂"), ("supset", "⊃"),
+        ("subseteq", "⊆"), ("supseteq", "⊇"), ("cup", "∪"), ("cap", "∩"), ("setminus", "∖"),
+        ("emptyset", "∅"), ("varnothing", "∅"), ("forall", "∀"), ("exists", "∃"),
+        ("nexists", "∄"), ("neg", "¬"), ("lnot", "¬"), ("land", "∧"), ("wedge", "∧"),
+        ("lor", "∨"), ("vee", "∨"), ("oplus", "⊕"), ("otimes", "⊗"), ("circ", "∘"),
+        ("bullet", "•"), ("star", "⋆"), ("cdots", "⋯"), ("ldots", "…"), ("dots", "…"),
+        ("vdots", "⋮"), ("ddots", "⋱"), ("prime", "′"), ("degree", "°"), ("angle", "∠"),
+        ("perp", "⊥"), ("parallel", "∥"), ("mid", "∣"), ("hbar", "ℏ"), ("ell", "ℓ"),
+        ("Re", "ℜ"), ("Im", "ℑ"), ("aleph", "ℵ"), ("therefore", "∴"), ("because", "∵"),
+        ("langle", "⟨"), ("rangle", "⟩"), ("lfloor", "⌊"), ("rfloor", "⌋"), ("lceil", "⌈"),
+        ("rceil", "⌉"), ("{", "{"), ("}", "}"), ("|", "‖"), ("%", "%"), ("$", "$"), ("_", "_"),
+        ("&", "&"), ("#", "#"), (",", " "), (";", " "), (":", " "), ("!", ""), (" ", " "),
+        ("quad", "  "), ("qquad", "    "), ("\\", "\n"),
+        ("sin", "sin"), ("cos", "cos"), ("tan", "tan"), ("sec", "sec"), ("csc", "csc"),
+        ("cot", "cot"), ("arcsin", "arcsin"), ("arccos", "arccos"), ("arctan", "arctan"),
+        ("sinh", "sinh"), ("cosh", "cosh"), ("tanh", "tanh"), ("log", "log"), ("ln", "ln"),
+        ("exp", "exp"), ("lim", "lim"), ("det", "det"), ("max", "max"), ("min", "min"),
+        ("sup", "sup"), ("inf", "inf"), ("gcd", "gcd"), ("deg", "deg"), ("dim", "dim"),
+    ].into_iter().collect();
+
+    static ref SUPERSCRIPTS: HashMap<char, char> = "0123456789+-=()abcdefghijklmnoprstuvwxyzABDEGHIJKLMNOPRTUVW".chars()
+        .zip("⁰¹²³⁴⁵⁶⁷⁸⁹⁺⁻⁼⁽⁾ᵃᵇᶜᵈᵉᶠᵍʰⁱʲᵏˡᵐⁿᵒᵖʳˢᵗᵘᵛʷˣʸᶻᴬᴮᴰᴱᴳᴴᴵᴶᴷᴸᴹᴺᴼᴾᴿᵀᵁⱽᵂ".chars())
+        .collect();
+
+    static ref SUBSCRIPTS: HashMap<char, char> = "0123456789+-=()aehijklmnoprstuvx".chars()
+        .zip("₀₁₂₃₄₅₆₇₈₉₊₋₌₍₎ₐₑₕᵢⱼₖₗₘₙₒₚᵣₛₜᵤᵥₓ".chars())
+        .collect();
+
+    static ref FRACTIONS: HashMap<(&'static str, &'static str), &'static str> = vec![
+        (("1", "2"), "½"), (("1", "3"), "⅓"), (("2", "3"), "⅔"), (("1", "4"), "¼"),
+        (("3", "4"), "¾"), (("1", "5"), "⅕"), (("2", "5"), "⅖"), (("3", "5"), "⅗"),
+        (("4", "5"), "⅘"), (("1", "6"), "⅙"), (("5", "6"), "⅚"), (("1", "8"), "⅛"),
+        (("3", "8"), "⅜"), (("5", "8"), "⅝"), (("7", "8"), "⅞"),
+    ].into_iter().collect();
+
+    static ref DOUBLE_STRUCK: HashMap<char, char> = "CHNPQRZ".chars()
+        .zip("ℂℍℕℙℚℝℤ".chars())
+        .collect();
+
+    // The longest command for a character wins, so `≤` becomes `\leq` rather than `\le`
+    static ref COMMANDS: HashMap<char, String> = {
+        let mut names: HashMap<char, &str> = HashMap::new();
+        for (name, sym) in SYMBOLS.iter() {
+            let mut chars = sym.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                if !c.is_ascii() && name.chars().all(|c| c.is_ascii_alphabetic()) {
+                    let best = names.entry(c).or_insert(name);
+                    if (name.len(), *best) > (best.len(), *name) {
+                        *best = name;
+                    }
+                }
+            }
+        }
+        let mut m: HashMap<char, String> = names.into_iter().map(|(c, n)| (c, format!("\\{}", n))).collect();
+        for (l, c) in DOUBLE_STRUCK.iter() {
+            m.insert(*c, format!("\\mathbb{{{}}}", l));
+        }
+        m.insert('√', String::from("\\sqrt"));
+        // There's no `\degree` without a package
+        m.insert('°', String::from("^\\circ"));
+        m.insert('‖', String::from("\\|"));
+        m
+    };
+
+    static ref FROM_SUPERSCRIPTS: HashMap<char, char> = SUPERSCRIPTS.iter().map(|(a, b)| (*b, *a)).collect();
+    static ref FROM_SUBSCRIPTS: HashMap<char, char> = SUBSCRIPTS.iter().map(|(a, b)| (*b, *a)).collect();
+}
+
+/// Best-effort plain text version of a LaTeX snippet, like `x²+√y`
+pub fn latex_to_unicode(s: &str) -> String {
+    let mut c = Converter {
+        chars: s.chars().collect(),
+        pos: 0,
+    };
+    let out = c.convert_until(None);
+
+    out.lines().map(|l| l.trim()).collect::<Vec<&str>>().join("\n")
+}
+
+/// Swaps Unicode math characters pasted from elsewhere for the LaTeX that latex understands,
+/// so `$∫₀¹ x² dx$` becomes `$\int _{0}^{1} x^{2} dx$`. Outside math they're wrapped in `\ensuremath`
+pub fn unicode_to_latex(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len());
+
+    // Which mode latex will be in, so text mode gets its math wrapped up
+    let mut math = false;
+    let mut depth = 0;
+    let mut text_depth: Option<usize> = None;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let in_math = math && text_depth.is_none();
+
+        match c {
+            '\\' => {
+                let name: String = chars[i+1..].iter().take_while(|c| c.is_ascii_alphabetic()).collect();
+                let next = chars.get(i + 1).cloned();
+                match (name.as_str(), next) {
+                    ("", Some('(')) | ("", Some('[')) => math = true,
+                    ("", Some(')')) | ("", Some(']')) => math = false,
+                    ("begin", _) | ("end", _) => {
+                        let env: String = chars[i+1+name.len()..].iter().skip(1).take_while(|c| **c != '}').collect();
+                        if MATH_ENVS.contains(&env.trim_end_matches('*')) {
+                            math = name == "begin";
+                        }
+                    },
+                    ("text", _) | ("textrm", _) | ("textbf", _) | ("textit", _) | ("mbox", _) if in_math => text_depth = Some(depth),
+                    // mhchem reads its own input, `\ce{Δ}` is fine and `\ce{\Delta}` isn't
+                    ("ce", _) | ("pu", _) => {
+                        let end = group_end(&chars, i + 1 + name.len());
+                        out.extend(chars[i..end].iter());
+                        i = end;
+                        continue;
+                    },
+                    _ => {},
+                }
+                // Copy the whole control sequence, so `\$` and friends don't change the mode
+                let len = if name.is_empty() { 2 } else { name.len() + 1 };
+                out.extend(chars[i..usize::min(i + len, chars.len())].iter());
+                i += len;
+                continue;
+            },
+            // `$$` opens or closes display math, not two lots of inline math
+            '$' if chars.get(i + 1) == Some(&'$') => {
+                math = !math;
+                out.push_str("$$");
+                i += 2;
+                continue;
+            },
+            '$' => math = !math,
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if text_depth == Some(depth) {
+                    text_depth = None;
+                }
+            },
+            _ => {},
+        }
+
+        let scripts: Option<(&HashMap<char, char>, &str, &str)> = if FROM_SUPERSCRIPTS.contains_key(&c) {
+            Some((&FROM_SUPERSCRIPTS, "^", "\\textsuperscript"))
+        } else if FROM_SUBSCRIPTS.contains_key(&c) {
+            Some((&FROM_SUBSCRIPTS, "_", "\\textsubscript"))
+        } else {
+            None
+        };
+
+        if let Some((map, math_cmd, text_cmd)) = scripts {
+            let run: String = chars[i..].iter().take_while(|c| map.contains_key(c)).map(|c| map[c]).collect();
+            i += run.chars().count();
+            if in_math {
+                out.push_str(&format!("{}{{{}}}", math_cmd, run));
+            } else {
+                out.push_str(&format!("{}{{{}}}", text_cmd, run));
+            }
+            continue;
+        }
+
+        match (c, COMMANDS.get(&c)) {
+            ('−', _) => out.push('-'),
+            ('′', _) if in_math => out.push('\''),
+            ('√', _) if !in_math => out.push_str("\\ensuremath{\\surd}"),
+            (_, Some(cmd)) if in_math => {
+                out.push_str(cmd);
+                out.push(' ');
+            },
+            (_, Some(cmd)) => out.push_str(&format!("\\ensuremath{{{}}}", cmd)),
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+/// Where the `{…}` group starting at `start` (after any spaces) ends, or `start` if there isn't one
+fn group_end(chars: &[char], start: usize) -> usize {
+    let open = start + chars[start..].iter().take_while(|c| **c == ' ').count();
+    if chars.get(open) != Some(&'{') {
+        return start
+    }
+
+    let mut depth = 0;
+    let mut i = open;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1
+                }
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+
+    chars.len()
+}
+
+const MATH_ENVS: [&str; 9] = ["equation", "align", "gather", "multline", "flalign", "alignat", "displaymath", "math", "eqnarray"];
+
+/// Markdown-escapes text so Discord shows it the way it was converted
+pub fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\*_~|`>".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn script(s: &str, map: &HashMap<char, char>, marker: char) -> String {
+    let s = s.trim();
+    let mapped: Option<String> = s.chars().map(|c| map.get(&c)).collect();
+
+    match mapped {
+        Some(m) if !m.is_empty() => m,
+        _ if s.chars().count() == 1 => format!("{}{}", marker, s),
+        _ => format!("{}({})", marker, s),
+    }
+}
+
+/// Puts brackets around anything that isn't a single "word"
+fn wrap(s: &str) -> String {
+    let s = s.trim();
+    if s.chars().all(|c| c.is_alphanumeric() || c == '.') || s.chars().count() == 1 {
+        String::from(s)
+    } else {
+        format!("({})", s)
+    }
+}
+
+struct Converter {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Converter {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn convert_until(&mut self, end: Option<char>) -> String {
+        let mut out = String::new();
+
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if Some(c) == end {
+                break;
+            }
+
+            match c {
+                '\\' => out.push_str(&self.command()),
+                '{' => out.push_str(&self.convert_until(Some('}'))),
+                '}' | '$' => {},
+                '^' => {
+                    let arg = self.arg();
+                    out.push_str(&script(&arg, &SUPERSCRIPTS, '^'));
+                },
+                '_' => {
+                    let arg = self.arg();
+                    out.push_str(&script(&arg, &SUBSCRIPTS, '_'));
+                },
+                '~' => out.push(' '),
+                '&' => out.push(' '),
+                '\'' => out.push('′'),
+                c => out.push(c),
+            }
+        }
+
+        out
+    }
+
+    fn raw_arg(&mut self) -> String {
+        while let Some(' ') = self.peek() {
+            self.pos += 1;
+        }
+
+        if let Some('{') = self.peek() {
+            self.pos += 1;
+            let mut depth = 0;
+            let mut out = String::new();
+            while let Some(c) = self.peek() {
+                self.pos += 1;
+                match c {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => break,
+                    '}' => depth -= 1,
+                    _ => {},
+                }
+                out.push(c);
+            }
+            out
+        } else {
+            self.pos += 1;
+            self.chars.get(self.pos - 1).map(|c| c.to_string()).unwrap_or_default()
+        }
+    }
+
+    fn arg(&mut self) -> String {
+        while let Some(' ') = self.peek() {
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                self.convert_until(Some('}'))
+            },
+            Some('\\') => {
+                self.pos += 1;
+                self.command()
+            },
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            },
+            None => String::new(),
+        }
+    }
+
+    fn command(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphabetic() {
+                name.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            if let Some(c) = self.peek() {
+                name.push(c);
+                self.pos += 1;
+            }
+        }
+
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let num = self.arg();
+                let den = self.arg();
+                match FRACTIONS.get(&(num.trim(), den.trim())) {
+                    Some(f) => String::from(*f),
+                    None => format!("{}/{}", wrap(&num), wrap(&den)),
+                }
+            },
+            "sqrt" => {
+                let mut root = "√";
+                if let Some('[') = self.peek() {
+                    let close = self.chars[self.pos..].iter().position(|c| *c == ']');
+                    if let Some(close) = close {
+                        let index: String = self.chars[self.pos+1..self.pos+close].iter().collect();
+                        self.pos += close + 1;
+                        root = match index.trim() {
+                            "3" => "∛",
+                            "4" => "∜",
+                            _ => "√",
+                        };
+                    }
+                }
+                format!("{}{}", root, wrap(&self.arg()))
+            },
+            "text" | "textrm" | "textbf" | "textit" | "mbox" | "operatorname" | "mathrm" => self.raw_arg(),
+            "mathbb" => {
+                self.arg().chars().map(|c| *DOUBLE_STRUCK.get(&c).unwrap_or(&c)).collect()
+            },
+            "mathbf" | "mathit" | "mathsf" | "mathcal" | "mathfrak" | "boldsymbol" => self.arg(),
+            "vec" => format!("{}\u{20d7}", self.arg()),
+            "hat" => format!("{}\u{302}", self.arg()),
+            "bar" | "overline" => format!("{}\u{305}", self.arg()),
+            "dot" => format!("{}\u{307}", self.arg()),
+            "ddot" => format!("{}\u{308}", self.arg()),
+            "tilde" => format!("{}\u{303}", self.arg()),
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "displaystyle" => String::new(),
+            "begin" | "end" => {
+                self.raw_arg();
+                String::new()
+            },
+            n => match SYMBOLS.get(n) {
+                Some(s) => String::from(*s),
+                None => format!("\\{}", n),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts() {
+        assert_eq!(latex_to_unicode("x^2+y_1"), "x²+y₁");
+        assert_eq!(latex_to_unicode("e^{i\\pi}"), "e^(iπ)");
+        assert_eq!(latex_to_unicode("a_{ij}^{n+1}"), "aᵢⱼⁿ⁺¹");
+        assert_eq!(latex_to_unicode("x^{\\alpha}"), "x^α");
+        assert_eq!(latex_to_unicode("x_{qy}"), "x_(qy)");
+    }
+
+    #[test]
+    fn symbols_and_roots() {
+        assert_eq!(latex_to_unicode(r"\alpha \leq \beta \to \infty"), "α ≤ β → ∞");
+        assert_eq!(latex_to_unicode(r"x^2+\sqrt{y}"), "x²+√y");
+        assert_eq!(latex_to_unicode(r"\sqrt[3]{x+1}"), "∛(x+1)");
+        assert_eq!(latex_to_unicode(r"\mathbb{R} \setminus \mathbb{Q}"), "ℝ ∖ ℚ");
+        assert_eq!(latex_to_unicode(r"\unknown{x}"), "\\unknownx");
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(latex_to_unicode(r"\frac{1}{2}"), "½");
+        assert_eq!(latex_to_unicode(r"\frac12"), "½");
+        assert_eq!(latex_to_unicode(r"\frac{a+b}{c}"), "(a+b)/c");
+        assert_eq!(latex_to_unicode(r"\dfrac{x}{2.5}"), "x/2.5");
+    }
+
+    #[test]
+    fn text_and_layout() {
+        assert_eq!(latex_to_unicode(r"$\text{if} x_n$"), "if xₙ");
+        assert_eq!(latex_to_unicode(r"\operatorname{sgn}(x)"), "sgn(x)");
+        assert_eq!(latex_to_unicode(r"\begin{aligned} a &= b \\ &= c \end{aligned}"), "a  = b\n= c");
+        assert_eq!(latex_to_unicode(r"\left( \vec{v} \right)"), "( v⃗ )");
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(escape_markdown("a_1 * b|c"), r"a\_1 \* b\|c");
+    }
+
+    #[test]
+    fn math_mode() {
+        assert_eq!(unicode_to_latex("$∫₀¹ x² dx$"), r"$\int _{0}^{1} x^{2} dx$");
+        assert_eq!(unicode_to_latex(r"\(α ≤ β\)"), r"\(\alpha  \leq  \beta \)");
+        assert_eq!(unicode_to_latex(r"\begin{align*} x ∈ ℝ \end{align*}"), r"\begin{align*} x \in  \mathbb{R}  \end{align*}");
+    }
+
+    #[test]
+    fn text_mode() {
+        assert_eq!(unicode_to_latex("∫₀¹ x²"), r"\ensuremath{\int}\textsubscript{0}\textsuperscript{1} x\textsuperscript{2}");
+        assert_eq!(unicode_to_latex(r"$\text{for α}$"), r"$\text{for \ensuremath{\alpha}}$");
+        assert_eq!(unicode_to_latex("√2"), r"\ensuremath{\surd}2");
+    }
+
+    #[test]
+    fn display_math() {
+        assert_eq!(unicode_to_latex("$$x²$$ and $y²$"), r"$$x^{2}$$ and $y^{2}$");
+    }
+
+    #[test]
+    fn degrees() {
+        assert_eq!(unicode_to_latex("$90°$"), r"$90^\circ $");
+        assert_eq!(unicode_to_latex("90°"), r"90\ensuremath{^\circ}");
+    }
+
+    #[test]
+    fn chemistry_untouched() {
+        assert_eq!(unicode_to_latex(r"$\ce{A ->[Δ] B} + Δ$"), r"$\ce{A ->[Δ] B} + \Delta $");
+        assert_eq!(unicode_to_latex(r"\pu {25 °C}"), r"\pu {25 °C}");
+        assert_eq!(unicode_to_latex(r"\ce{H2O^{α}"), r"\ce{H2O^{α}");
+    }
+
+    #[test]
+    fn escapes_and_minus() {
+        assert_eq!(unicode_to_latex(r"\$ −α"), r"\$ -\ensuremath{\alpha}");
+    }
+}
@@ -2,9 +2,11 @@ use serenity::{
     async_trait,
     builder::{
         CreateButton,
+        CreateSelectMenu,
+        CreateSelectMenuKind,
         CreateSelectMenuOption,
         CreateActionRow,
-        CreateComponents
+        CreateMessage,
     },
     model::{
         prelude::{
@@ -12,9 +14,22 @@ use serenity::{
             Interaction,
             ReactionType,
         },
-        channel::Message,
-        interactions::message_component::ButtonStyle,
-        id::MessageId,
+        channel::{
+            GuildChannel,
+            Message,
+        },
+        guild::{
+            Guild,
+            Member,
+        },
+        Permissions,
+        user::User,
+        application::ButtonStyle,
+        id::{
+            ChannelId,
+            MessageId,
+            UserId,
+        },
     },
     prelude::{
         Context,
@@ -48,6 +63,8 @@ pub struct BotModule {
 pub trait Editable {
     async fn edit(&mut self, ctx: &Context) -> Result<(), errors::Error>;
     fn get_response_message_id(&self) -> Vec<MessageId>;
+    /// Responses may not share the input's channel (e.g. DM output), so cleanup goes through these
+    fn get_response_messages(&self) -> Vec<(ChannelId, MessageId)>;
     fn get_input_message_id(&self) -> MessageId;
     fn get_command_pattern(&self) -> Regex;
 }
@@ -90,11 +107,76 @@ pub async fn push_to_editables(ctx: &Context, i: Box<dyn Editable + Send + Sync>
     }
 }
 
+pub async fn pull_from_interactables(ctx: &Context, ids: &Vec<MessageId>) {
+    let interactables_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<Interactables>().expect("Oops!").clone() //TODO: Error handling
+    };
+
+    {
+        let mut interactables = interactables_lock.write().await;
+        interactables.retain(|i| {
+            !i.get_response_message_id().iter().any(|j| ids.contains(j))
+        });
+    }
+}
+
+/// Forgets everything made for these input messages, and removes it from the channel
+pub async fn clean_up_inputs(ctx: &Context, ids: &Vec<MessageId>) {
+    let editables_lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
+    };
+
+    let mut responses: Vec<(ChannelId, MessageId)> = vec![];
+
+    {
+        let mut editables = editables_lock.write().await;
+        editables.retain(|i| {
+            if ids.contains(&i.get_input_message_id()) {
+                responses.extend(i.get_response_messages());
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    if responses.len() > 0 {
+        delete_responses(ctx, &responses).await.ok();
+        pull_from_interactables(ctx, &responses.iter().map(|(_, m_id)| *m_id).collect()).await;
+    }
+}
+
+pub async fn delete_responses(ctx: &Context, responses: &[(ChannelId, MessageId)]) -> Result<(), SerenityError> {
+    let mut res = Ok(());
+
+    for (c_id, m_id) in responses {
+        if let Err(e) = c_id.delete_message(&ctx.http, *m_id).await {
+            res = Err(e);   // Keep going, some of these might already be gone
+        }
+    }
+
+    res
+}
+
 #[derive(Debug)]
 pub enum Buttons {
     Delete,
+    Dismiss,
+    Source,
+    ZoomIn,
+    ZoomOut,
+    Download,
+    ReadAloud,
+    Save,
+    Export,
     Next,
     Prev,
+    ShowAll,
+    Gif,
+    Fix,
+    Edit,
     Invalid     // Not for actual use
 }
 
@@ -102,8 +184,20 @@ impl ToString for Buttons {
     fn to_string(&self) -> String {
         match &self {
             Buttons::Delete => "Delete".to_string(),
+            Buttons::Dismiss => "Dismiss".to_string(),
+            Buttons::Source => "Source".to_string(),
+            Buttons::ZoomIn => "Zoom +".to_string(),
+            Buttons::ZoomOut => "Zoom \u{2212}".to_string(),
+            Buttons::Download => "Download".to_string(),
+            Buttons::ReadAloud => "Read aloud".to_string(),
+            Buttons::Save => "Save".to_string(),
+            Buttons::Export => "Export".to_string(),
             Buttons::Next => "Next".to_string(),
             Buttons::Prev => "Previous".to_string(),
+            Buttons::ShowAll => "Show all".to_string(),
+            Buttons::Gif => "GIF".to_string(),
+            Buttons::Fix => "Use suggestion".to_string(),
+            Buttons::Edit => "Edit".to_string(),
             Buttons::Invalid => "".to_string(),
         }
     }
@@ -113,8 +207,20 @@ impl From<&str> for Buttons {
     fn from(s: &str) -> Buttons {
         match s {
             "DEL" => Buttons::Delete,
+            "DIS" => Buttons::Dismiss,
+            "SRC" => Buttons::Source,
+            "ZIN" => Buttons::ZoomIn,
+            "ZOU" => Buttons::ZoomOut,
+            "DWN" => Buttons::Download,
+            "SPK" => Buttons::ReadAloud,
+            "SAV" => Buttons::Save,
+            "EXP" => Buttons::Export,
             "NEX" => Buttons::Next,
             "PRE" => Buttons::Prev,
+            "ALL" => Buttons::ShowAll,
+            "GIF" => Buttons::Gif,
+            "FIX" => Buttons::Fix,
+            "EDT" => Buttons::Edit,
             _ => Buttons::Invalid,
         }
     }
@@ -124,8 +230,20 @@ impl Buttons {
     fn to_id_string(&self) -> String {
         match &self {
             Buttons::Delete => "DEL".to_string(),
+            Buttons::Dismiss => "DIS".to_string(),
+            Buttons::Source => "SRC".to_string(),
+            Buttons::ZoomIn => "ZIN".to_string(),
+            Buttons::ZoomOut => "ZOU".to_string(),
+            Buttons::Download => "DWN".to_string(),
+            Buttons::ReadAloud => "SPK".to_string(),
+            Buttons::Save => "SAV".to_string(),
+            Buttons::Export => "EXP".to_string(),
             Buttons::Next => "NEX".to_string(),
             Buttons::Prev => "PRE".to_string(),
+            Buttons::ShowAll => "ALL".to_string(),
+            Buttons::Gif => "GIF".to_string(),
+            Buttons::Fix => "FIX".to_string(),
+            Buttons::Edit => "EDT".to_string(),
             Buttons::Invalid => "".to_string(),
         }
     }
@@ -133,8 +251,20 @@ impl Buttons {
     fn to_emoji(&self) -> ReactionType {
         match &self {
             Buttons::Delete => ReactionType::Unicode("🗑️".to_string()),
+            Buttons::Dismiss => ReactionType::Unicode("\u{1f515}".to_string()),
+            Buttons::Source => ReactionType::Unicode("\u{1f4dc}".to_string()),
+            Buttons::ZoomIn => ReactionType::Unicode("\u{2795}".to_string()),
+            Buttons::ZoomOut => ReactionType::Unicode("\u{2796}".to_string()),
+            Buttons::Download => ReactionType::Unicode("\u{1f4be}".to_string()),
+            Buttons::ReadAloud => ReactionType::Unicode("\u{1f50a}".to_string()),
+            Buttons::Save => ReactionType::Unicode("\u{1f4cc}".to_string()),
+            Buttons::Export => ReactionType::Unicode("\u{1f4e6}".to_string()),
             Buttons::Next => ReactionType::Unicode("\u{27a1}".to_string()),
             Buttons::Prev => ReactionType::Unicode("\u{2b05}".to_string()),
+            Buttons::ShowAll => ReactionType::Unicode("\u{23ec}".to_string()),
+            Buttons::Gif => ReactionType::Unicode("\u{1f39e}".to_string()),
+            Buttons::Fix => ReactionType::Unicode("\u{1fa84}".to_string()),
+            Buttons::Edit => ReactionType::Unicode("\u{270f}\u{fe0f}".to_string()),
             Buttons::Invalid => ReactionType::Unicode("\u{1f6ab}".to_string()),
         }
     }
@@ -142,44 +272,32 @@ impl Buttons {
     fn buttonstyle(&self) -> ButtonStyle {
         match &self {
             Buttons::Delete => ButtonStyle::Danger,
+            Buttons::Dismiss | Buttons::Source | Buttons::Edit | Buttons::Download | Buttons::ReadAloud | Buttons::Save | Buttons::Export | Buttons::Gif => ButtonStyle::Secondary,
             _ => ButtonStyle::Primary,
         }
     }
 
-    pub fn to_button(&self) -> impl FnOnce(&mut CreateButton) -> &mut CreateButton + '_ {
-        let label = self.to_string();
-        let id = self.to_id_string();
-        let emoji = self.to_emoji();
-        let style = self.buttonstyle();
-        move |b| {
-            b.style(style);
-            b.label(label);
-            b.custom_id(id);
-            b.emoji(emoji);
-            b.disabled(false);
-            b
+    /// Whether only the person who asked for the output can use this button
+    pub fn author_only(&self) -> bool {
+        match &self {
+            // Edit does its own check, people who can manage messages get to use it too
+            Buttons::Source | Buttons::Download | Buttons::ReadAloud | Buttons::Save | Buttons::Export | Buttons::Gif | Buttons::Edit => false,
+            _ => true,
         }
     }
 
-    pub fn add_buttons<'b>(a: &'b mut CreateComponents, vb: Vec<Buttons>) -> &'b mut CreateComponents {
-        let mut ib = vb.into_iter();
-        
-        let mut rows: Vec<CreateActionRow> = vec![];
-        
-        while ib.len() > 0 {
-            rows.push(CreateActionRow::default());
-            let i = rows.len();
-            for _ in 0..3 {
-                if let Some(b) = ib.next() {
-                    rows[i-1].create_button(b.to_button());
-                } else {
-                    continue;
-                }
-            }
-        }
+    pub fn to_button(&self) -> CreateButton {
+        CreateButton::new(self.to_id_string())
+            .style(self.buttonstyle())
+            .label(self.to_string())
+            .emoji(self.to_emoji())
+            .disabled(false)
+    }
 
-        a.set_action_rows(rows);
-        a
+    pub fn add_buttons(vb: Vec<Buttons>) -> Vec<CreateActionRow> {
+        vb.chunks(3)
+            .map(|r| CreateActionRow::Buttons(r.iter().map(|b| b.to_button()).collect()))
+            .collect()
     }
 }
 
@@ -202,38 +320,173 @@ impl MenuItem {
     }
     
     pub fn to_csmop(&self) -> CreateSelectMenuOption {
-        let mut i = CreateSelectMenuOption::default();
-        i.label(self.label.clone());
-        i.value(self.value.clone());
+        let mut i = CreateSelectMenuOption::new(self.label.clone(), self.value.clone())
+            .default_selection(false)
+            .description(self.description.clone());
         if let Some(e) = self.emoji.clone() {
-            i.emoji(e);
+            i = i.emoji(e);
         }
-        i.default_selection(false);
-        i.description(self.description.clone());
         i
     }
 
-    pub fn add_menu<'b>(c: &'b mut CreateComponents, vmi: Vec<MenuItem>, custom_id: &str) -> &'b mut CreateComponents {
-        c.create_action_row( |a| {
-            a.create_select_menu( |sm| {
-                sm.options( |smops| {
-                    for i in vmi {
-                        smops.add_option(i.to_csmop());
-                    }
-                    smops
-                });
-                sm.custom_id(custom_id);
-                sm
-            });
-            a
-        });
-        c
+    pub fn add_menu(vmi: Vec<MenuItem>, custom_id: &str) -> CreateActionRow {
+        let options = vmi.iter().map(|i| i.to_csmop()).collect();
+        CreateActionRow::SelectMenu(CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options }))
     }
 }
 
-pub async fn loading_msg(ctx: &Context, c_id: &serenity::model::id::ChannelId) -> Result<Message, SerenityError> {
-    c_id.send_message(&ctx.http, |m| {
-        m.content("Doing stuff <a:loading:840650882286223371>");
-        m
-    }).await
+/// Splits leading `--flags` off a command's arguments, returning the ones found and the rest
+pub fn strip_flags<'a>(arg: &'a str, flags: &[&str]) -> (Vec<String>, &'a str) {
+    let mut found = vec![];
+    let mut rest = arg.trim_start();
+
+    'outer: loop {
+        for f in flags {
+            if rest.starts_with(f) && rest[f.len()..].chars().next().map_or(true, |c| c.is_whitespace()) {
+                found.push(String::from(*f));
+                rest = rest[f.len()..].trim_start();
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    (found, rest)
+}
+
+/// What the bot is allowed to post in a channel, from least to most
+#[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
+pub enum OutputLevel {
+    Nothing,
+    Text,
+    File,
+    Embed,
+}
+
+pub async fn output_level(ctx: &Context, c_id: &ChannelId) -> OutputLevel {
+    // DMs, and anything not in the cache, are assumed to allow everything
+    let channel = match ctx.cache.channel(*c_id) {
+        Some(c) => c.clone(),
+        None => {return OutputLevel::Embed}
+    };
+
+    let guild = match ctx.cache.guild(channel.guild_id) {
+        Some(g) => g,
+        None => {return OutputLevel::Embed}
+    };
+
+    channel_level(&guild, &channel, ctx.cache.current_user().id)
+}
+
+/// What a user can post in one of a guild's channels, overwrites included
+fn channel_level(guild: &Guild, channel: &GuildChannel, user: UserId) -> OutputLevel {
+    match guild.members.get(&user) {
+        Some(m) => OutputLevel::from(guild.user_permissions_in(channel, m)),
+        None => OutputLevel::Embed,
+    }
+}
+
+impl From<Permissions> for OutputLevel {
+    fn from(perms: Permissions) -> OutputLevel {
+        if !perms.send_messages() {
+            OutputLevel::Nothing
+        } else if !perms.attach_files() {
+            OutputLevel::Text
+        } else if !perms.embed_links() {
+            OutputLevel::File
+        } else {
+            OutputLevel::Embed
+        }
+    }
+}
+
+/// Whether the member behind an interaction can manage messages in its channel, nobody can in DMs
+pub fn can_manage_messages(member: Option<&Member>) -> bool {
+    member.and_then(|m| m.permissions).is_some_and(|p| p.manage_messages())
+}
+
+/// Where to send output for a user, their DMs if the bot can't post in the channel
+pub async fn output_target(ctx: &Context, c_id: &ChannelId, for_user: &User) -> Result<(ChannelId, OutputLevel), SerenityError> {
+    match output_level(ctx, c_id).await {
+        OutputLevel::Nothing => Ok((for_user.create_dm_channel(&ctx).await?.id, OutputLevel::Embed)),
+        l => Ok((*c_id, l)),
+    }
+}
+
+pub async fn loading_msg(ctx: &Context, c_id: &ChannelId, for_user: &User) -> Result<Message, SerenityError> {
+    let (c_id, _) = output_target(ctx, c_id, for_user).await?;
+
+    c_id.send_message(&ctx.http, CreateMessage::new().content("Doing stuff <a:loading:840650882286223371>")).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::model::{
+        channel::{
+            PermissionOverwrite,
+            PermissionOverwriteType,
+        },
+        guild::Role,
+        id::{
+            GuildId,
+            RoleId,
+        },
+    };
+
+    /// A guild where everyone can post embeds and files, and a channel denying `deny` to the bot
+    fn guild_with(deny: Permissions) -> (Guild, GuildChannel, UserId) {
+        let bot = UserId::new(3);
+        let mut guild = Guild::default();
+        guild.id = GuildId::new(1);
+        guild.owner_id = UserId::new(2);
+
+        let mut everyone = Role::default();
+        everyone.id = RoleId::new(1);
+        everyone.permissions = Permissions::SEND_MESSAGES | Permissions::ATTACH_FILES | Permissions::EMBED_LINKS | Permissions::VIEW_CHANNEL;
+        guild.roles.insert(everyone.id, everyone);
+
+        let mut member = Member::default();
+        member.user.id = bot;
+        member.guild_id = guild.id;
+        guild.members.insert(bot, member);
+
+        let mut channel = GuildChannel::default();
+        channel.id = ChannelId::new(4);
+        channel.guild_id = guild.id;
+        channel.permission_overwrites.push(PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny,
+            kind: PermissionOverwriteType::Member(bot),
+        });
+
+        (guild, channel, bot)
+    }
+
+    #[test]
+    fn channel_overwrites() {
+        for (deny, level) in [
+            (Permissions::empty(), OutputLevel::Embed),
+            (Permissions::EMBED_LINKS, OutputLevel::File),
+            (Permissions::ATTACH_FILES, OutputLevel::Text),
+            (Permissions::ATTACH_FILES | Permissions::EMBED_LINKS, OutputLevel::Text),
+            (Permissions::SEND_MESSAGES, OutputLevel::Nothing),
+        ] {
+            let (guild, channel, bot) = guild_with(deny);
+            assert_eq!(channel_level(&guild, &channel, bot), level, "denying {:?}", deny);
+        }
+    }
+
+    #[test]
+    fn unknown_member() {
+        let (guild, channel, _) = guild_with(Permissions::SEND_MESSAGES);
+        assert_eq!(channel_level(&guild, &channel, UserId::new(5)), OutputLevel::Embed);
+    }
+
+    #[test]
+    fn levels_are_ordered() {
+        assert!(OutputLevel::Nothing < OutputLevel::Text);
+        assert!(OutputLevel::Text < OutputLevel::File);
+        assert!(OutputLevel::File < OutputLevel::Embed);
+    }
 }
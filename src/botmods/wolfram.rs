@@ -11,6 +11,10 @@ use crate::{
         errors,
         utils::{
             loading_msg,
+            output_level,
+            output_target,
+            OutputLevel,
+            strip_flags,
             Buttons,
             MenuItem,
             BotModule,
@@ -35,19 +39,23 @@ use serenity::{
         },
         Args,
     },
+    builder::{
+        CreateActionRow,
+        CreateAttachment,
+        CreateEmbed,
+        CreateEmbedFooter,
+        CreateInteractionResponse,
+        CreateMessage,
+        EditMessage,
+    },
     model::{
         channel::Message,
         id::{
             ChannelId,
             MessageId,
         },
-        interactions::{
-            message_component::{
-                ComponentType,
-                InteractionMessage,
-            },
-            InteractionResponseType,
-            InteractionApplicationCommandCallbackDataFlags,
+        application::{
+            ComponentInteractionDataKind,
             Interaction,
         },
         prelude::MessageUpdateEvent,
@@ -67,8 +75,8 @@ lazy_static!(
     pub static ref MOD_WOLFRAM: BotModule = BotModule {
         command_group: &WOLFRAM_GROUP,
         command_pattern: vec![
-            Regex::new(format!(r"^{}wolfram .*$", PREFIX.as_str()).as_str()).unwrap(),
-            Regex::new(format!(r"^{}w .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}wolfram .*$", PREFIX.as_str()).as_str()).unwrap(),
+            Regex::new(format!(r"(?s)^{}w .*$", PREFIX.as_str()).as_str()).unwrap(),
         ],
         editors: vec![
             edit_handler_wrap,
@@ -112,10 +120,12 @@ pub enum CmdType {
     Wolfram
 }
 
+const FLAGS: [&str; 1] = ["--spoiler"];
+
 lazy_static!{
     pub static ref EDITMATCH: Vec<(Regex, CmdType)> = vec![
-        (Regex::new(format!(r"^{}wolfram (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Wolfram),
-        (Regex::new(format!(r"^{}w (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Wolfram),
+        (Regex::new(format!(r"(?s)^{}wolfram (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Wolfram),
+        (Regex::new(format!(r"(?s)^{}w (?P<i>.*)$", PREFIX.as_str()).as_str()).unwrap(), CmdType::Wolfram),
     ];
 }
 
@@ -130,12 +140,12 @@ pub async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
     };
 
     lazy_static! {
-        static ref WOLFRAM_RE: Regex = Regex::new(format!(r"^{}wolfram (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-        static ref ALIAS_RE: Regex = Regex::new(format!(r"^{}w (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        static ref WOLFRAM_RE: Regex = Regex::new(format!(r"(?s)^{}wolfram (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+        static ref ALIAS_RE: Regex = Regex::new(format!(r"(?s)^{}w (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
     };
 
     let opts = vec![
-        Opt::Format("image".to_string()),
+        Opt::Format("image,plaintext".to_string()),
         Opt::Output("json".to_string()),
     ];
 
@@ -149,15 +159,17 @@ pub async fn edit_handler(ctx: Context, msg_upd_event: MessageUpdateEvent) {
         return
     }
 
-    let lm = loading_msg(&ctx, &inp_message.channel_id).await.unwrap();
+    let lm = loading_msg(&ctx, &inp_message.channel_id, &inp_message.author).await.unwrap();
+
+    let (flags, arg) = strip_flags(arg, &FLAGS);
 
     let new_w = QueryResult::new(Opt::Input(arg.to_string()), opts).await.unwrap();
-    let mut new_wm = WolfMessage::new(new_w.clone(), inp_message.clone(), new_w.pods).await;
+    let mut new_wm = WolfMessage::new(new_w.clone(), inp_message.clone(), new_w.pods, flags.iter().any(|f| f == "--spoiler")).await;
 
     lm.delete(&ctx).await.unwrap();
     new_wm.send_messages(&ctx).await;
-    push_to_editables(&ctx, Box::new(new_wm)).await;
-    // push_to_interactables(&ctx, Box::new(new_wm)).await;
+    push_to_editables(&ctx, Box::new(new_wm.clone())).await;
+    push_to_interactables(&ctx, Box::new(new_wm.clone())).await;
 }
 
 // pub fn component_interaction_handler_wrap(ctx: Context, interaction: Interaction) -> Pin<Box<dyn Future<Output = ()> + Send>> {
@@ -356,6 +368,7 @@ impl Pod {
 pub struct Subpod {
     title: String,
     image: Image,
+    plaintext: String,
     json: Value
 }
 
@@ -364,6 +377,7 @@ impl Subpod {
         Subpod {
             title: json["title"].as_str().unwrap().to_string(),
             image: Image::new(&json["img"]).await,
+            plaintext: json["plaintext"].as_str().unwrap_or("").to_string(),
             json: json.clone()
         }
     }
@@ -401,12 +415,12 @@ pub struct WolfMessage {
 }
 
 impl WolfMessage {
-    async fn new(r: QueryResult, inp: Message, pods: Vec<Pod>) -> WolfMessage {
+    async fn new(r: QueryResult, inp: Message, pods: Vec<Pod>, hidden: bool) -> WolfMessage {
         let mut pod_messages = vec![];
 
         for i in pods.iter() {
             pod_messages.push(
-                PodMessage::new(i).await
+                PodMessage::new(i, hidden).await
             );
         }
         
@@ -431,41 +445,52 @@ impl WolfMessage {
                 MenuItem::new(j.pod.title.clone(), None, format!("POD{}", i), format!("Pod {}", i+1))
             )
         }
-        
-        self.header_message = Some(self.inp_message.channel_id.send_message(&ctx.http, |m|{
-            m.embed(|e| {
-                e.title("Wolfram query");
-                e.description("Results provided by [Wolfram|Alpha](https://www.wolframalpha.com/)");
-                if let Opt::Input(s) = &self.result.input {
-                    e.field("Input", s, false);
-                }
-                if m_items.len() == 0 {
-                    if self.result.json["didyoumeans"].is_object() {
-                        e.field("No result found!", format!("Did you mean:\n{}", self.result.json["didyoumeans"]["val"].as_str().unwrap()), false);
-                    } else {
-                        e.field("Uh oh", "No result found!", false);
-                    }
-                }
-                e.footer(|f| {
-                    if let Some(u) = self.inp_message.author.avatar_url() {
-                        f.icon_url(u);
-                    } else {
-                        f.icon_url(self.inp_message.author.default_avatar_url());
-                    }
-                    f.text(format!("Requested by {}#{}", self.inp_message.author.name, self.inp_message.author.discriminator));
-                    f
-                });
-                e
-            });
-            m.components(|c| {
-                if m_items.len() > 0 {
-                    MenuItem::add_menu(c, m_items, "POD");
-                }
-                Buttons::add_buttons(c, buttons);
-                c
-            });
-            m
-        }).await.unwrap());
+
+        let (c_id, level) = output_target(ctx, &self.inp_message.channel_id, &self.inp_message.author).await.unwrap();
+
+        let no_result = m_items.len() == 0;
+        let mut components = vec![];
+        if !no_result {
+            components.push(MenuItem::add_menu(m_items, "POD"));
+        }
+        components.extend(Buttons::add_buttons(buttons));
+
+        if level < OutputLevel::Embed {
+            let mut content = String::from("**Wolfram query**, results provided by Wolfram|Alpha");
+            if let Opt::Input(s) = &self.result.input {
+                content.push_str(&format!("\nInput: {}", s));
+            }
+            if no_result {
+                content.push_str("\nNo result found!");
+            }
+            content.push_str(&format!("\n*Requested by {}*", self.inp_message.author.tag()));
+
+            self.header_message = Some(c_id.send_message(&ctx.http, CreateMessage::new()
+                .content(content)
+                .components(components)
+            ).await.unwrap());
+            return
+        }
+
+        let mut e = CreateEmbed::new()
+            .title("Wolfram query")
+            .description("Results provided by [Wolfram|Alpha](https://www.wolframalpha.com/)");
+        if let Opt::Input(s) = &self.result.input {
+            e = e.field("Input", s, false);
+        }
+        if no_result {
+            if self.result.json["didyoumeans"].is_object() {
+                e = e.field("No result found!", format!("Did you mean:\n{}", self.result.json["didyoumeans"]["val"].as_str().unwrap()), false);
+            } else {
+                e = e.field("Uh oh", "No result found!", false);
+            }
+        }
+        let e = e.footer(CreateEmbedFooter::new(format!("Requested by {}", self.inp_message.author.tag())).icon_url(self.inp_message.author.face()));
+
+        self.header_message = Some(c_id.send_message(&ctx.http, CreateMessage::new()
+            .embed(e)
+            .components(components)
+        ).await.unwrap());
         
     } //TODO: Error handling
     
@@ -485,22 +510,55 @@ impl WolfMessage {
 pub struct PodMessage {
     pod: Pod,
     curr_spod: usize,
+    text_only: bool,
+    hidden: bool,
     pub message: Option<Message>,
 }
 
 impl PodMessage {
-    async fn new(pod: &Pod) -> PodMessage {
+    async fn new(pod: &Pod, hidden: bool) -> PodMessage {
         PodMessage {
             pod: pod.clone(),
             curr_spod: 0,
+            text_only: false,
+            hidden,
             message: None
         }
     }
 
+    /// Wolfram's plaintext result, for channels where embeds can't be used
+    fn text_content(&self, spod: usize) -> String {
+        let mut text = self.pod.subpods[spod].plaintext.replace("```", "`\u{200b}``");
+        if text.is_empty() {
+            text = String::from("No text result, this one only comes as an image");
+        }
+        if text.len() > 1900 {
+            let mut end = 1897;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push_str("...");
+        }
+        if self.hidden {
+            format!("**{}**\n||```\n{}\n```||", self.pod.title, text)
+        } else {
+            format!("**{}**\n```\n{}\n```", self.pod.title, text)
+        }
+    }
+
     async fn send_message(&mut self, ctx: &Context, channel_id: ChannelId) -> Result<(), errors::Error> {
         if let Some(_) = &self.message {
             return Ok(())
         }
+
+        let level = output_level(ctx, &channel_id).await;
+        self.text_only = level < OutputLevel::Embed;
+
+        self.post(ctx, channel_id, 0).await
+    }
+
+    async fn post(&mut self, ctx: &Context, channel_id: ChannelId, spod: usize) -> Result<(), errors::Error> {
         let mut buttons: Vec<Buttons> = vec![
             Buttons::Delete,
         ];
@@ -511,24 +569,30 @@ impl PodMessage {
             ]);
         }
 
-        let mut buttons = buttons.into_iter();
+        // Embed images can't be spoilered, hidden ones get uploaded as an attachment instead
+        let hidden_image = if self.hidden && !self.text_only {
+            Some(reqwest::get(&self.pod.subpods[spod].image.src).await?.bytes().await?.to_vec())
+        } else {
+            None
+        };
 
-        self.message = Some(channel_id.send_message(&ctx.http, |m|{
-            m.embed( |e| {
-                e.title(&self.pod.title);
-                e.image(&self.pod.subpods[0].image.src);
-                e
-            });
-            m.components(|c| {
-                c.create_action_row(|a| {
-                    for _ in 0..buttons.len() {
-                        a.create_button(buttons.next().unwrap().to_button());
-                    }
-                    a
-                })
-            });
-            m
-        }).await?);
+        let m = if self.text_only {
+            CreateMessage::new().content(self.text_content(spod))
+        } else if let Some(i) = hidden_image {
+            CreateMessage::new()
+                .embed(CreateEmbed::new().title(&self.pod.title))
+                .add_file(CreateAttachment::bytes(i, "SPOILER_pod.gif"))
+        } else {
+            CreateMessage::new().embed(
+                CreateEmbed::new()
+                    .title(&self.pod.title)
+                    .image(&self.pod.subpods[spod].image.src)
+            )
+        };
+        let row = CreateActionRow::Buttons(buttons.iter().map(|b| b.to_button()).collect());
+
+        self.message = Some(channel_id.send_message(&ctx.http, m.components(vec![row])).await?);
+        self.curr_spod = spod;
 
         Ok(())
     }
@@ -543,14 +607,22 @@ impl PodMessage {
     
     async fn change_spod(&mut self, ctx: &Context, spod: usize) -> Result<(), errors::Error> {
         if let Some(mut sm) = self.message.clone() {
-            sm.edit(&ctx.http, |m| {
-                m.embed( |e| {
-                    e.title(&self.pod.title);
-                    e.image(&self.pod.subpods[spod].image.src);
-                    e
-                });
-                m
-            }).await?;
+            if self.hidden && !self.text_only {
+                // Attachments can't be edited, so the pod is sent again
+                sm.delete(ctx).await?;
+                return self.post(ctx, sm.channel_id, spod).await
+            }
+
+            let m = if self.text_only {
+                EditMessage::new().content(self.text_content(spod))
+            } else {
+                EditMessage::new().embed(
+                    CreateEmbed::new()
+                        .title(&self.pod.title)
+                        .image(&self.pod.subpods[spod].image.src)
+                )
+            };
+            sm.edit(&ctx.http, m).await?;
             self.curr_spod = spod;
         }
         Ok(())
@@ -564,12 +636,12 @@ impl Editable for WolfMessage {
         self.delete(&ctx).await;
 
         lazy_static! {
-            static ref WOLFRAM_RE: Regex = Regex::new(format!(r"^{}wolfram (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
-            static ref ALIAS_RE: Regex = Regex::new(format!(r"^{}w (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+            static ref WOLFRAM_RE: Regex = Regex::new(format!(r"(?s)^{}wolfram (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
+            static ref ALIAS_RE: Regex = Regex::new(format!(r"(?s)^{}w (?P<args>.*)$", PREFIX.as_str()).as_str()).unwrap();
         };
 
         let opts = vec![
-            Opt::Format("image".to_string()),
+            Opt::Format("image,plaintext".to_string()),
             Opt::Output("json".to_string()),
         ];
 
@@ -583,11 +655,13 @@ impl Editable for WolfMessage {
             arg = c.name("args").unwrap().as_str();
         }
 
+        let (flags, arg) = strip_flags(arg, &FLAGS);
+
         let new_w = QueryResult::new(Opt::Input(arg.to_string()), opts).await?;
-        let mut new_wm = WolfMessage::new(new_w.clone(), inp_message.clone(), new_w.pods).await;
+        let mut new_wm = WolfMessage::new(new_w.clone(), inp_message.clone(), new_w.pods, flags.iter().any(|f| f == "--spoiler")).await;
 
-        self = &mut new_wm;
-        self.send_messages(&ctx).await;
+        new_wm.send_messages(&ctx).await;
+        *self = new_wm;
 
         let interactables_lock = {
             let data_read = ctx.data.read().await;
@@ -636,10 +710,17 @@ impl Editable for WolfMessage {
         return retvec;
     }
 
+    fn get_response_messages(&self) -> Vec<(serenity::model::id::ChannelId, MessageId)> {
+        self.header_message.iter()
+            .chain(self.pod_messages.iter().filter_map(|p| p.message.as_ref()))
+            .map(|m| (m.channel_id, m.id))
+            .collect()
+    }
+
     fn get_command_pattern(&self) -> Regex {
         lazy_static! {
-            static ref WOLFRAM_RE: Regex = Regex::new(format!(r"^{}wolfram .*$", PREFIX.as_str()).as_str()).unwrap();
-            static ref ALIAS_RE: Regex = Regex::new(format!(r"^{}w .*$", PREFIX.as_str()).as_str()).unwrap();
+            static ref WOLFRAM_RE: Regex = Regex::new(format!(r"(?s)^{}wolfram .*$", PREFIX.as_str()).as_str()).unwrap();
+            static ref ALIAS_RE: Regex = Regex::new(format!(r"(?s)^{}w .*$", PREFIX.as_str()).as_str()).unwrap();
         };
 
         if WOLFRAM_RE.is_match(&self.inp_message.content) {
@@ -657,30 +738,20 @@ impl Interactable for WolfMessage {
     async fn interaction_respond(&mut self, ctx: &Context, interaction: Interaction) -> Result<(), errors::Error> {
         let old_m = self.header_message.clone();
         let component_interaction = match interaction {
-            Interaction::MessageComponent(m) => m,
+            Interaction::Component(m) => m,
             _ => {return Ok(())}
         };
         
-        component_interaction.create_interaction_response(ctx, |r|{
-            r.kind(InteractionResponseType::UpdateMessage);
-            r.interaction_response_data(|d|{
-                d.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
-                d
-            });
-            r
-        }).await?;
-
-        let message = match &component_interaction.message {
-            InteractionMessage::Regular(m) => m,
-            InteractionMessage::Ephemeral(_) => {return Ok(())}
-        };
+        component_interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+
+        let message = &component_interaction.message;
 
         if self.inp_message.author != component_interaction.user {
             return Ok(())
         }
 
-        match &component_interaction.data.component_type {
-            ComponentType::Button => {
+        match &component_interaction.data.kind {
+            ComponentInteractionDataKind::Button => {
                 for i in self.pod_messages.iter_mut() {
                     if i.message.is_some() && i.message.as_ref().unwrap().id == message.id {
                         match Buttons::from(component_interaction.data.custom_id.as_str()) {
@@ -715,9 +786,9 @@ impl Interactable for WolfMessage {
                     }
                 }
             },
-            ComponentType::SelectMenu => {
+            ComponentInteractionDataKind::StringSelect { values } => {
                 if self.header_message.is_some() && self.header_message.as_ref().unwrap().id == message.id {
-                    for v in &component_interaction.data.values {
+                    for v in values {
                         lazy_static! {
                             static ref POD_RE: Regex = Regex::new(r"^POD(?P<n>\d+)").unwrap();
                         }; 
@@ -782,8 +853,10 @@ impl Interactable for WolfMessage {
 
 #[command]
 #[aliases("w")]
+#[description = "Ask Wolfram|Alpha, pass --spoiler to hide the answers"]
+#[usage = "[--spoiler] <query>"]
 pub async fn wolfram(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
-    let lm = loading_msg(&ctx, &msg.channel_id).await?;
+    let lm = loading_msg(&ctx, &msg.channel_id, &msg.author).await?;
     let query = match arg.remains() {
         Some(r) => Ok(r),
         None => {
@@ -794,13 +867,15 @@ pub async fn wolfram(ctx: &Context, msg: &Message, arg: Args) -> CommandResult {
     }?;
     
     let opts = vec![
-        Opt::Format("image".to_string()),
+        Opt::Format("image,plaintext".to_string()),
         Opt::Output("json".to_string()),
         ];
     
+    let (flags, query) = strip_flags(query, &FLAGS);
+    
     let w = QueryResult::new(Opt::Input(query.to_string()), opts).await?;
     
-    let mut wm = WolfMessage::new(w.clone(), msg.clone(), w.pods).await;
+    let mut wm = WolfMessage::new(w.clone(), msg.clone(), w.pods, flags.iter().any(|f| f == "--spoiler")).await;
 
     lm.delete(&ctx.http).await?;
     
@@ -1,12 +1,14 @@
 use serenity::{
     async_trait,
     model::{
-        gateway::{
-            Ready,
-            Activity,
-        },
+        gateway::Ready,
         channel::Message,
-        id::UserId,
+        id::{
+            UserId,
+            ChannelId,
+            MessageId,
+            GuildId,
+        },
         event::MessageUpdateEvent,
         prelude::Interaction
     },
@@ -22,9 +24,11 @@ use serenity::{
         CommandResult,
         Args,
     },
-    client::bridge::gateway::ShardManager,
+    gateway::{
+        ActivityData,
+        ShardManager,
+    },
 };
-use tokio::sync::Mutex;
 use std::{
     collections::{
         HashSet,
@@ -43,10 +47,28 @@ use serde::Deserialize;
 #[macro_use]
 extern crate lazy_static;
 
+pub mod api;
 pub mod botmods;
+pub mod lint;
+pub mod render;
 use botmods::utils::{
     Editable,
     Interactable,
+    delete_responses,
+    push_to_editables,
+    push_to_interactables,
+    pull_from_interactables,
+    clean_up_inputs,
+};
+use botmods::settings::{
+    GuildSettingsMap,
+    get_settings,
+    load_settings,
+};
+use botmods::markup::RenderStats;
+use botmods::snippets::{
+    SnippetLibrary,
+    load_snippets,
 };
 
 lazy_static!{
@@ -61,7 +83,16 @@ pub static INTERACT_BUFFER_SIZE: usize = 10;
 pub struct Config {
     pub discord_token: String,
     pub discord_appid: u64,
-    prefix: String
+    prefix: String,
+    /// The local HTTP API is only started when this is set
+    #[serde(default)]
+    pub api: Option<ApiConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct ApiConfig {
+    pub port: u16,
+    pub keys: Vec<String>,
 }
 
 fn load_config() -> Config {
@@ -80,7 +111,7 @@ fn load_config() -> Config {
 pub struct ShardManagerContainer;
 
 impl TypeMapKey for ShardManagerContainer {
-    type Value = Arc<Mutex<ShardManager>>;
+    type Value = Arc<ShardManager>;
 }
 
 pub struct Editables;
@@ -101,7 +132,7 @@ pub struct Handler;
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("Connected as {}", ready.user.name);
-        ctx.set_activity(Activity::listening(format!("{}help", PREFIX.as_str()))).await;
+        ctx.set_activity(Some(ActivityData::listening(format!("{}help", PREFIX.as_str()))));
     }
     
     async fn message(&self, ctx: Context, msg: Message) {
@@ -124,20 +155,38 @@ impl EventHandler for Handler {
             data_read.get::<Editables>().expect("Oops!").clone() //TODO: Error handling
         };
 
-        {
+        let new_content = match &upd_event.content {
+            Some(c) => c.clone(),
+            None => {return}
+        };
+
+        // Taken out while it's edited, so the lock isn't held across rendering and requests to Discord
+        let editable = {
             let mut editables = editables_lock.write().await;
-            editables.make_contiguous();
-            
-            for i in editables.iter_mut() {
-                if i.get_input_message_id() == upd_event.id {
-                    i.edit(&ctx).await.unwrap();
-                    return
+            editables.iter()
+                .position(|i| i.get_input_message_id() == upd_event.id)
+                .and_then(|p| editables.remove(p))
+        };
+
+        if let Some(mut e) = editable {
+            if e.get_command_pattern().is_match(&new_content) {
+                if let Err(err) = e.edit(&ctx).await {
+                    eprintln!("Error updating the output of an edited message:\n{}", err);
                 }
+                push_to_editables(&ctx, e).await;
+                return
             }
+
+            // The command changed, the old module gives up the input message
+            delete_responses(&ctx, &e.get_response_messages()).await.ok();
+            pull_from_interactables(&ctx, &e.get_response_message_id()).await;
         }
 
         let mut editor_futures: Vec<Pin<Box<dyn Future<Output = ()> + Send>>> = vec![];
         for m in botmods::MODS.iter() {
+            if !m.command_pattern.iter().any(|r| r.is_match(&new_content)) {
+                continue;
+            }
             for editor in &m.editors {
                 editor_futures.push(editor(ctx.clone(), upd_event.clone()));
             }
@@ -145,23 +194,53 @@ impl EventHandler for Handler {
         join_all(editor_futures).await;
     }
     
+    async fn message_delete(&self, ctx: Context, channel_id: ChannelId, deleted_message_id: MessageId, guild_id: Option<GuildId>) {
+        let settings = get_settings(&ctx, guild_id).await;
+        if !settings.cleanup_on_delete && !settings.math_channels.contains(&channel_id.get()) {
+            return
+        }
+
+        clean_up_inputs(&ctx, &vec![deleted_message_id]).await;
+    }
+
+    async fn message_delete_bulk(&self, ctx: Context, channel_id: ChannelId, multiple_deleted_messages_ids: Vec<MessageId>, guild_id: Option<GuildId>) {
+        let settings = get_settings(&ctx, guild_id).await;
+        if !settings.cleanup_on_delete && !settings.math_channels.contains(&channel_id.get()) {
+            return
+        }
+
+        clean_up_inputs(&ctx, &multiple_deleted_messages_ids).await;
+    }
+    
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         let interactables_lock = {
             let data_read = ctx.data.read().await;
             data_read.get::<Interactables>().expect("Oops!").clone() //TODO: Error handling
         };
 
-        {
-            let mut interactables = interactables_lock.write().await;
-            interactables.make_contiguous();
-            
-            for i in interactables.iter_mut() {
-                for j in i.get_response_message_id() {
-                    if interaction.clone().message_component().is_some() && interaction.clone().message_component().unwrap().message.regular().is_some() && interaction.clone().message_component().unwrap().message.regular().unwrap().id == j {
-                        i.interaction_respond(&ctx, interaction.clone()).await.unwrap();
-                    }
-                }
+        // Modal submits come from a button, and carry the message that button was on
+        let m_id = match &interaction {
+            Interaction::Component(c) => Some(c.message.id),
+            Interaction::Modal(m) => m.message.as_ref().map(|m| m.id),
+            _ => None,
+        };
+
+        // Taken out while it responds, so the lock isn't held across requests to Discord
+        let target = match m_id {
+            Some(m_id) => {
+                let mut interactables = interactables_lock.write().await;
+                interactables.iter()
+                    .position(|i| i.get_response_message_id().contains(&m_id))
+                    .and_then(|p| interactables.remove(p))
+            },
+            None => None,
+        };
+
+        if let Some(mut i) = target {
+            if let Err(e) = i.interaction_respond(&ctx, interaction.clone()).await {
+                eprintln!("Error responding to an interaction:\n{}", e);
             }
+            push_to_interactables(&ctx, i).await;
         }
 
         let mut interactor_futures: Vec<Pin<Box<dyn Future<Output = ()> + Send>>> = vec![];
@@ -204,4 +283,7 @@ pub async fn load_queues(c: &Client) {
     data.insert::<ShardManagerContainer>(Arc::clone(&c.shard_manager));
     data.insert::<Editables>(Arc::new(RwLock::new(VecDeque::with_capacity(EDIT_BUFFER_SIZE))));
     data.insert::<Interactables>(Arc::new(RwLock::new(VecDeque::with_capacity(INTERACT_BUFFER_SIZE))));
+    data.insert::<GuildSettingsMap>(Arc::new(RwLock::new(load_settings())));
+    data.insert::<SnippetLibrary>(Arc::new(RwLock::new(load_snippets())));
+    data.insert::<RenderStats>(Arc::new(RwLock::new(Default::default())));
 }
@@ -0,0 +1,608 @@
+use std::fmt::{
+    self,
+    Display,
+};
+
+/// Environments that start display math on their own
+const DISPLAY_ENVS: [&str; 8] = ["equation", "align", "gather", "multline", "flalign", "alignat", "eqnarray", "displaymath"];
+/// Environments that only work inside math
+const INNER_ENVS: [&str; 15] = [
+    "aligned", "alignedat", "gathered", "split", "array", "cases", "dcases", "rcases",
+    "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix",
+];
+/// Environments where `&` separates columns
+const ALIGN_ENVS: [&str; 19] = [
+    "align", "flalign", "alignat", "eqnarray", "aligned", "alignedat", "split", "array", "tabular",
+    "cases", "dcases", "rcases", "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix",
+];
+/// Commands whose argument is text, even in the middle of math
+const TEXT_COMMANDS: [&str; 9] = ["text", "textrm", "textbf", "textit", "textsf", "texttt", "textnormal", "mbox", "intertext"];
+/// Commands with arguments in their own little language, which the linter stays out of
+const OPAQUE_COMMANDS: [(&str, usize); 11] = [
+    ("ce", 1), ("pu", 1), ("si", 1), ("SI", 2), ("num", 1), ("qty", 2), ("unit", 1),
+    ("label", 1), ("ref", 1), ("eqref", 1), ("ensuremath", 1),
+];
+/// Commands defining macros or environments, and how many `{…}` arguments they take with the name
+/// in braces. The name and body are never linted, since `#1` can stand for anything
+const DEFINERS: [(&str, usize); 6] = [
+    ("newcommand", 2), ("renewcommand", 2), ("providecommand", 2), ("DeclareMathOperator", 2),
+    ("newenvironment", 3), ("renewenvironment", 3),
+];
+/// Font switches from plain TeX, and what to use instead
+const OLD_FONTS: [(&str, &str); 8] = [
+    ("rm", "mathrm"), ("bf", "mathbf"), ("it", "mathit"), ("sf", "mathsf"), ("tt", "mathtt"),
+    ("cal", "mathcal"), ("sl", "textsl"), ("sc", "textsc"),
+];
+/// More errors than this and the rest are probably the same mistake
+const MAX_ERRORS: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    /// Compiles, but probably not into what was meant
+    Warning,
+    /// Would make latex fail
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Opener {
+    Brace,
+    /// The argument of `\text` and friends
+    Text,
+    /// The argument of something like `\ce`
+    Opaque,
+    Dollar,
+    DoubleDollar,
+    Bracket,
+    Paren,
+    Left,
+    Env,
+}
+
+struct Frame {
+    opener: Opener,
+    env: String,
+    math: bool,
+    pos: (usize, usize),
+    /// Arguments still to come for the command this was an argument of
+    rest: Vec<Opener>,
+}
+
+struct Linter {
+    chars: Vec<char>,
+    i: usize,
+    line: usize,
+    column: usize,
+    multiline: bool,
+    stack: Vec<Frame>,
+    /// What the next `{` opens, for commands that take arguments
+    pending: Vec<Opener>,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn at(&self, pos: (usize, usize)) -> String {
+        if self.multiline {
+            format!("line {}, column {}", pos.0, pos.1)
+        } else {
+            format!("column {}", pos.1)
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.i)?;
+        self.i += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).cloned()
+    }
+
+    fn warn(&mut self, message: String) {
+        self.lints.push(Lint { severity: Severity::Warning, message });
+    }
+
+    fn error(&mut self, message: String) {
+        self.lints.push(Lint { severity: Severity::Error, message });
+    }
+
+    fn math(&self) -> bool {
+        matches!(self.stack.last(), Some(f) if f.math)
+    }
+
+    fn opaque(&self) -> bool {
+        self.stack.iter().any(|f| f.opener == Opener::Opaque)
+    }
+
+    /// The closest frame that decides what `&`, `_` and `^` mean, skipping plain groups
+    fn enclosing(&self) -> Option<&Frame> {
+        self.stack.iter().rev().find(|f| !matches!(f.opener, Opener::Brace | Opener::Left))
+    }
+
+    fn push(&mut self, opener: Opener, env: &str, math: bool, pos: (usize, usize)) {
+        let rest = match opener {
+            Opener::Text | Opener::Opaque => std::mem::take(&mut self.pending),
+            _ => vec![],
+        };
+        self.stack.push(Frame { opener, env: String::from(env), math, pos, rest });
+    }
+
+    fn opener_str(f: &Frame) -> String {
+        match f.opener {
+            Opener::Brace | Opener::Text | Opener::Opaque => String::from("`{`"),
+            Opener::Dollar => String::from("`$`"),
+            Opener::DoubleDollar => String::from("`$$`"),
+            Opener::Bracket => String::from(r"`\[`"),
+            Opener::Paren => String::from(r"`\(`"),
+            Opener::Left => String::from(r"`\left`"),
+            Opener::Env => format!(r"`\begin{{{}}}`", f.env),
+        }
+    }
+
+    fn unclosed(&self, f: &Frame) -> String {
+        match f.opener {
+            Opener::Left => format!(r"`\left` at {} has no matching `\right`", self.at(f.pos)),
+            Opener::Env => format!(r"`\begin{{{}}}` at {} has no `\end{{{}}}`", f.env, self.at(f.pos), f.env),
+            _ => format!("{} at {} is never closed", Linter::opener_str(f), self.at(f.pos)),
+        }
+    }
+
+    /// Closes the innermost frame, which has to be one `closes` accepts. Mismatches can't be linted past
+    fn close(&mut self, closer: &str, pos: (usize, usize), closes: impl Fn(&Frame) -> bool) -> Result<(), ()> {
+        let top = match self.stack.last() {
+            Some(f) if closes(f) => {
+                let f = self.stack.pop().unwrap();
+                self.pending = f.rest;
+                return Ok(())
+            },
+            Some(f) => f,
+            None => {
+                self.error(format!("{} at {} doesn't close anything", closer, self.at(pos)));
+                return Err(())
+            },
+        };
+
+        let message = if self.stack.iter().any(&closes) {
+            format!("{}, it's still open at the {} at {}", self.unclosed(top), closer, self.at(pos))
+        } else if top.opener == Opener::Env && closer.starts_with(r"`\end") {
+            format!("{} at {} doesn't match the {} at {}", closer, self.at(pos), Linter::opener_str(top), self.at(top.pos))
+        } else {
+            format!("{} at {} doesn't close anything", closer, self.at(pos))
+        };
+        self.error(message);
+        Err(())
+    }
+
+    /// The letters of a control word, or the one character of a control symbol
+    fn control_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphabetic() {
+                break
+            }
+            name.push(c);
+            self.next();
+        }
+        if name.is_empty() {
+            if let Some(c) = self.next() {
+                name.push(c);
+            }
+        }
+        name
+    }
+
+    /// The `{name}` after `\begin` or `\end`
+    fn env_name(&mut self) -> Option<String> {
+        while self.peek() == Some(' ') {
+            self.next();
+        }
+        if self.peek() != Some('{') {
+            return None
+        }
+        self.next();
+        let mut name = String::new();
+        while let Some(c) = self.next() {
+            if c == '}' {
+                return Some(name)
+            }
+            name.push(c);
+        }
+        None
+    }
+
+    /// Skips the `\name` right after `\newcommand` or `\def`, returning false if it's a `{\name}` argument instead
+    fn macro_name(&mut self) -> bool {
+        while self.peek() == Some(' ') {
+            self.next();
+        }
+        if self.peek() != Some('\\') {
+            return false
+        }
+        self.next();
+        self.control_name();
+        true
+    }
+
+    /// Skips an optional `[…]` argument, braces inside it included
+    fn optional_arg(&mut self) {
+        let mut depth = 0;
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ']' if depth == 0 => return,
+                _ => {},
+            }
+        }
+    }
+
+    fn command(&mut self, name: &str, pos: (usize, usize)) -> Result<(), ()> {
+        let math = self.math();
+        let base_env = |n: &str| String::from(n.trim_end_matches('*'));
+
+        match name {
+            "[" | "(" => {
+                let opener = if name == "[" {Opener::Bracket} else {Opener::Paren};
+                if math {
+                    self.error(format!(r"`\{}` at {} is already inside math", name, self.at(pos)));
+                    return Err(())
+                }
+                self.push(opener, "", true, pos);
+            },
+            "]" => self.close(r"`\]`", pos, |f| f.opener == Opener::Bracket)?,
+            ")" => self.close(r"`\)`", pos, |f| f.opener == Opener::Paren)?,
+            "begin" => {
+                let env = match self.env_name() {
+                    Some(e) => e,
+                    None => {return Ok(())}
+                };
+                let base = base_env(&env);
+                let env_math = if base == "tabular" {
+                    false
+                } else if DISPLAY_ENVS.contains(&base.as_str()) {
+                    if math {
+                        self.error(format!(r"`\begin{{{}}}` at {} can't go inside math, try `aligned` or `gathered`", env, self.at(pos)));
+                    }
+                    true
+                } else if INNER_ENVS.contains(&base.as_str()) {
+                    if !math {
+                        self.error(format!(r"`\begin{{{}}}` at {} needs to be inside math, wrap it in `\[…\]`", env, self.at(pos)));
+                    }
+                    true
+                } else {
+                    math || base == "tikzcd"
+                };
+                self.push(Opener::Env, &env, env_math, pos);
+            },
+            "end" => {
+                let env = match self.env_name() {
+                    Some(e) => e,
+                    None => {return Ok(())}
+                };
+                let closer = format!(r"`\end{{{}}}`", env);
+                self.close(&closer, pos, |f| f.opener == Opener::Env && f.env == env)?;
+            },
+            "left" => {
+                if !math {
+                    self.error(format!(r"`\left` at {} needs to be inside math", self.at(pos)));
+                }
+                self.push(Opener::Left, "", true, pos);
+            },
+            "right" => self.close(r"`\right`", pos, |f| f.opener == Opener::Left)?,
+            "def" | "gdef" | "edef" | "xdef" => {
+                // Everything between the name and the body is the parameter text, like `#1#2`
+                self.macro_name();
+                while matches!(self.peek(), Some(c) if c != '{') {
+                    self.next();
+                }
+                self.pending = vec![Opener::Opaque];
+            },
+            "verb" => {
+                if self.peek() == Some('*') {
+                    self.next();
+                }
+                // Any character can be the delimiter, `\verb|a_b|` or `\verb+a_b+`
+                if let Some(delim) = self.next() {
+                    while matches!(self.next(), Some(c) if c != delim) {}
+                }
+                self.pending.clear();
+            },
+            "over" => self.warn(format!(r"`\over` at {} is plain TeX, `\frac{{…}}{{…}}` is easier to get right", self.at(pos))),
+            n if TEXT_COMMANDS.contains(&n) => self.pending = vec![Opener::Text],
+            n => {
+                if let Some((_, args)) = OPAQUE_COMMANDS.iter().find(|(c, _)| *c == n) {
+                    self.pending = vec![Opener::Opaque; *args];
+                } else if let Some((_, args)) = DEFINERS.iter().find(|(c, _)| *c == n) {
+                    if self.peek() == Some('*') {
+                        self.next();
+                    }
+                    let args = if self.macro_name() {args - 1} else {*args};
+                    self.pending = vec![Opener::Opaque; args];
+                } else if let Some((_, new)) = OLD_FONTS.iter().find(|(c, _)| *c == n) {
+                    // `{\bf bold}` in text wants `\textbf`, there's no text `\cal` to suggest
+                    let new = if math || n == "cal" {String::from(*new)} else {new.replace("math", "text")};
+                    self.warn(format!(r"`\{}` at {} is an old font switch, use `\{}{{…}}` instead", n, self.at(pos), new));
+                    self.pending.clear();
+                } else {
+                    self.pending.clear();
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn dollar(&mut self, pos: (usize, usize)) -> Result<(), ()> {
+        let top = self.stack.last().map(|f| f.opener);
+
+        // `$x$$y$` is two bits of inline math, not a display
+        if top == Some(Opener::Dollar) {
+            self.stack.pop();
+            return Ok(())
+        }
+
+        let double = self.peek() == Some('$');
+        if double {
+            self.next();
+        }
+        let (opener, closer) = if double {(Opener::DoubleDollar, "`$$`")} else {(Opener::Dollar, "`$`")};
+
+        if top == Some(opener) {
+            self.stack.pop();
+        } else if !self.math() {
+            self.push(opener, "", true, pos);
+        } else if self.stack.iter().any(|f| f.opener == opener) {
+            self.close(closer, pos, |f| f.opener == opener)?;
+        } else {
+            self.error(format!("{} at {} is inside math already, there's no need for it", closer, self.at(pos)));
+            return Err(())
+        }
+        Ok(())
+    }
+
+    fn script(&mut self, c: char, pos: (usize, usize)) {
+        if self.math() {
+            return
+        }
+        let message = match self.enclosing().map(|f| f.opener) {
+            Some(Opener::Text) => match c {
+                '_' => format!(r"`_` at {} is inside `\text{{}}`, write `\_` or wrap the math in `$…$`", self.at(pos)),
+                _ => format!(r"`^` at {} is inside `\text{{}}`, use `\textsuperscript{{}}` or wrap the math in `$…$`", self.at(pos)),
+            },
+            _ => format!("`{}` at {} is outside math, wrap the math in `$…$`", c, self.at(pos)),
+        };
+        self.error(message);
+    }
+
+    fn ampersand(&mut self, pos: (usize, usize)) {
+        // `\pmat{a & b}` can be a macro that sets up its own alignment, there's no telling
+        if matches!(self.stack.last(), Some(f) if f.opener == Opener::Brace) {
+            return
+        }
+        let aligned = match self.enclosing() {
+            Some(f) => f.opener == Opener::Env && (ALIGN_ENVS.contains(&f.env.trim_end_matches('*')) || f.env == "tikzcd"),
+            None => false,
+        };
+        if !aligned {
+            self.error(format!(r"`&` at {} is outside an alignment like `aligned` or `matrix`, write `\&` for an ampersand", self.at(pos)));
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(c) = self.next() {
+            let pos = (self.line, self.column);
+
+            if self.opaque() {
+                let result = match c {
+                    '\\' => {self.control_name(); Ok(())},
+                    '{' => {self.push(Opener::Opaque, "", self.math(), pos); Ok(())},
+                    '}' => self.close("`}`", pos, |f| f.opener == Opener::Opaque),
+                    _ => Ok(()),
+                };
+                if result.is_err() {
+                    return
+                }
+                continue
+            }
+
+            let result = match c {
+                '%' => {
+                    while matches!(self.peek(), Some(c) if c != '\n') {
+                        self.next();
+                    }
+                    Ok(())
+                },
+                '\\' => {
+                    let name = self.control_name();
+                    self.command(&name, pos)
+                },
+                '{' => {
+                    let opener = if self.pending.is_empty() {Opener::Brace} else {self.pending.remove(0)};
+                    let math = match opener {
+                        Opener::Text => false,
+                        _ => self.math(),
+                    };
+                    self.push(opener, "", math, pos);
+                    Ok(())
+                },
+                '[' if !self.pending.is_empty() => {self.optional_arg(); Ok(())},
+                '}' => self.close("`}`", pos, |f| matches!(f.opener, Opener::Brace | Opener::Text | Opener::Opaque)),
+                '$' => self.dollar(pos),
+                '&' => {self.ampersand(pos); Ok(())},
+                '_' | '^' => {self.script(c, pos); Ok(())},
+                c if c.is_whitespace() => Ok(()),
+                _ => {self.pending.clear(); Ok(())},
+            };
+
+            if result.is_err() {
+                return
+            }
+        }
+
+        if let Some(f) = self.stack.last() {
+            let message = self.unclosed(f);
+            self.error(message);
+        }
+    }
+}
+
+/// Catches the mistakes that would make latex fail, without running it. Errors come first
+pub fn lint(source: &str) -> Vec<Lint> {
+    let mut linter = Linter {
+        chars: source.chars().collect(),
+        i: 0,
+        line: 1,
+        column: 0,
+        multiline: source.trim().contains('\n'),
+        stack: vec![],
+        pending: vec![],
+        lints: vec![],
+    };
+    linter.run();
+
+    let (errors, warnings): (Vec<Lint>, Vec<Lint>) = linter.lints.into_iter().partition(|l| l.severity == Severity::Error);
+    errors.into_iter().take(MAX_ERRORS).chain(warnings).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str) -> Vec<String> {
+        lint(source).into_iter().filter(|l| l.severity == Severity::Error).map(|l| l.message).collect()
+    }
+
+    fn warnings(source: &str) -> Vec<String> {
+        lint(source).into_iter().filter(|l| l.severity == Severity::Warning).map(|l| l.message).collect()
+    }
+
+    #[test]
+    fn clean_snippets() {
+        for s in [
+            r"$\frac{a}{b}$",
+            r"\[\left( \sum_{i=1}^n x_i \right)^2\]",
+            r"$\begin{pmatrix} a & b \\ c & d \end{pmatrix}$",
+            r"\begin{align} x &= 1 \\ y &= 2 \end{align}",
+            r"\text{The value } $x_1$ \text{ is } \textbf{big}",
+            r"$x$$y$",
+            r"$$\int_0^1 f$$",
+            r"$\ce{H2O}$ and \SI{3}{m/s^2}",
+            r"50\% off, \& more",
+        ] {
+            assert!(lint(s).is_empty(), "`{}` gave {:?}", s, lint(s));
+        }
+    }
+
+    #[test]
+    fn unbalanced_braces() {
+        assert_eq!(errors(r"$\frac{a}{b$"), vec!["`{` at column 10 is never closed, it's still open at the `$` at column 12"]);
+        assert_eq!(errors(r"\frac{a}{b"), vec!["`{` at column 9 is never closed"]);
+        assert_eq!(errors(r"a}"), vec!["`}` at column 2 doesn't close anything"]);
+    }
+
+    #[test]
+    fn left_without_right() {
+        assert_eq!(errors(r"$\left( x$"), vec![r"`\left` at column 2 has no matching `\right`, it's still open at the `$` at column 10"]);
+        assert_eq!(errors(r"\[\left( x\]"), vec![r"`\left` at column 3 has no matching `\right`, it's still open at the `\]` at column 11"]);
+        assert_eq!(errors(r"$x \right)$"), vec![r"`\right` at column 4 doesn't close anything"]);
+        assert_eq!(errors(r"\left( x \right)"), vec![r"`\left` at column 1 needs to be inside math"]);
+    }
+
+    #[test]
+    fn ampersand_outside_alignment() {
+        let message = |c| format!(r"`&` at column {} is outside an alignment like `aligned` or `matrix`, write `\&` for an ampersand", c);
+        assert_eq!(errors(r"$a & b$"), vec![message(4)]);
+        assert_eq!(errors(r"Tom & Jerry"), vec![message(5)]);
+        assert_eq!(errors(r"$\left( a & b \right)$"), vec![message(11)]);
+        assert_eq!(errors(r"$\begin{pmatrix} a & {b} \end{pmatrix}$"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn script_in_text() {
+        assert_eq!(errors(r"$\text{x_1}$"), vec![r"`_` at column 9 is inside `\text{}`, write `\_` or wrap the math in `$…$`"]);
+        assert_eq!(errors(r"\text{x^2}"), vec![r"`^` at column 8 is inside `\text{}`, use `\textsuperscript{}` or wrap the math in `$…$`"]);
+        assert_eq!(errors(r"\text{$x_1$}"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn script_outside_math() {
+        assert_eq!(errors(r"x_1"), vec!["`_` at column 2 is outside math, wrap the math in `$…$`"]);
+    }
+
+    #[test]
+    fn stray_dollar_in_math() {
+        assert_eq!(errors(r"\[ x $ \]"), vec!["`$` at column 6 is inside math already, there's no need for it"]);
+        assert_eq!(errors(r"$$ x $ y $$"), vec!["`$` at column 6 is inside math already, there's no need for it"]);
+    }
+
+    #[test]
+    fn environments() {
+        assert_eq!(errors(r"$\begin{align} x \end{align}$"), vec![r"`\begin{align}` at column 2 can't go inside math, try `aligned` or `gathered`"]);
+        assert_eq!(errors(r"\begin{cases} x \end{cases}"), vec![r"`\begin{cases}` at column 1 needs to be inside math, wrap it in `\[…\]`"]);
+        assert_eq!(errors(r"$\begin{matrix} x \end{pmatrix}$"), vec![r"`\end{pmatrix}` at column 19 doesn't match the `\begin{matrix}` at column 2"]);
+    }
+
+    #[test]
+    fn line_numbers() {
+        assert_eq!(errors("\\begin{align}\nx &= \\frac{1}{2\n\\end{align}"), vec![r"`{` at line 2, column 14 is never closed, it's still open at the `\end{align}` at line 3, column 1"]);
+    }
+
+    #[test]
+    fn old_fonts() {
+        assert_eq!(warnings(r"$\bf x$"), vec![r"`\bf` at column 2 is an old font switch, use `\mathbf{…}` instead"]);
+        assert_eq!(warnings(r"{\bf bold}"), vec![r"`\bf` at column 2 is an old font switch, use `\textbf{…}` instead"]);
+        assert_eq!(warnings(r"${a \over b}$"), vec![r"`\over` at column 5 is plain TeX, `\frac{…}{…}` is easier to get right"]);
+    }
+
+    #[test]
+    fn errors_first_and_capped() {
+        let lints = lint(r"{\bf x} a_1 b_2 c_3 d_4");
+        assert_eq!(lints.len(), MAX_ERRORS + 1);
+        assert!(lints[..MAX_ERRORS].iter().all(|l| l.severity == Severity::Error));
+        assert_eq!(lints[MAX_ERRORS].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn macro_definitions() {
+        for s in [
+            r"\newcommand{\xs}{x_1,\dots,x_n} $\xs$",
+            r"\newcommand\xs{x_1,\dots,x_n} $\xs$",
+            r"\renewcommand*{\vec}[1]{\mathbf{#1}} $\vec{v}_1$",
+            r"\def\abs#1{\left|#1\right|} $\abs{x}$",
+            r"\newcommand{\pmat}[1]{\begin{pmatrix}#1\end{pmatrix}} $\pmat{a & b}$",
+            r"\newcommand{\opt}[2][x_0]{#1^#2} $\opt{2}$",
+            r"\newenvironment{proofsketch}{\begin{proof}[Sketch]}{\end{proof}}",
+            r"\DeclareMathOperator{\Tr}{Tr} $\Tr A$",
+            r"\DeclareMathOperator*{\argmax}{arg\,max_x}",
+        ] {
+            assert!(lint(s).is_empty(), "`{}` gave {:?}", s, lint(s));
+        }
+        assert_eq!(errors(r"\newcommand{\xs}{x} x_1"), vec!["`_` at column 22 is outside math, wrap the math in `$…$`"]);
+    }
+
+    #[test]
+    fn opaque_arguments() {
+        assert!(lint(r"\ensuremath{x_1}").is_empty());
+        assert!(lint(r"\verb|a_b|").is_empty());
+        assert!(lint(r"\verb*+{a & b+ $x$").is_empty());
+        assert_eq!(errors(r"\verb|a_b| c_d"), vec!["`_` at column 13 is outside math, wrap the math in `$…$`"]);
+    }
+}
@@ -1,9 +1,15 @@
 use serenity::{
     prelude::*,
-    framework::standard::StandardFramework,
-    client::bridge::gateway::GatewayIntents,
+    framework::standard::{
+        StandardFramework,
+        Configuration,
+    },
+    http::Http,
+};
+use std::{
+    collections::HashSet,
+    process,
 };
-use std::process;
 use wabot::{
     unknown_cmd,
     Handler,
@@ -11,6 +17,7 @@ use wabot::{
     PREFIX,
     CONFIG,
     load_queues,
+    api,
     botmods::MODS
 };
 
@@ -20,20 +27,38 @@ async fn main() {
     let token = &CONFIG.discord_token;
     let application_id = CONFIG.discord_appid;
 
+    // Owners get the commands hidden from everyone else
+    let owners = match Http::new(token).get_current_application_info().await {
+        Ok(info) => {
+            let mut owners = HashSet::new();
+            match (info.team, info.owner) {
+                (Some(team), _) => owners.extend(team.members.iter().map(|m| m.user.id)),
+                (None, Some(owner)) => {owners.insert(owner.id);},
+                (None, None) => {},
+            }
+            owners
+        },
+        Err(e) => {
+            eprintln!("Error getting the application info: {}", e);
+            process::exit(1);
+        }
+    };
+
     let mut framework = StandardFramework::new()
-        .configure(|c| c.prefix(&PREFIX))
         .help(&HELP)
         .unrecognised_command(unknown_cmd);
+    framework.configure(Configuration::new().prefix(PREFIX.as_str()).owners(owners));
 
     for m in MODS.iter() {
         framework.group_add(m.command_group);
     }
 
-    let mut bot = match Client::builder(token)
+    // Guilds fills the cache with the channels and roles that permission checks need
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+    let mut bot = match Client::builder(token, intents)
         .event_handler(Handler)
         .framework(framework)
-        .application_id(application_id)
-        .intents(GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES)
+        .application_id(application_id.into())
         .await
         {
         Ok(c) => c,
@@ -45,6 +70,10 @@ async fn main() {
     
     load_queues(&bot).await;
 
+    if let Some(config) = &CONFIG.api {
+        tokio::spawn(api::serve(config));
+    }
+
     if let Err(e) = bot.start_autosharded().await {
         eprintln!("Client error: {}", e);
     }
@@ -0,0 +1,418 @@
+use std::{
+    collections::VecDeque,
+    error::Error as StdErr,
+    fmt,
+    io,
+    path::Path,
+    process::Output,
+    sync::Mutex,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+#[allow(unused_imports)] use usvg::SystemFontDB;
+use usvg;
+use tiny_skia::Color;
+use tempfile;
+use regex::Regex;
+use tokio::process::Command;
+use serde::{
+    Serialize,
+    Deserialize
+};
+use lazy_static;
+
+/// What can go wrong compiling or rasterizing, the bot turns these into its own errors
+#[derive(Debug)]
+pub enum RenderError {
+    SVGError(usvg::Error),
+    PNGError(png::EncodingError),
+    IOError(io::Error),
+    GifError(gif::EncodingError),
+    /// What latex, MathJax or dvisvgm had to say about the input
+    MathError(String),
+    NoImgError(),
+}
+
+impl From<usvg::Error> for RenderError {
+    fn from(e: usvg::Error) -> RenderError {
+        RenderError::SVGError(e)
+    }
+}
+
+impl From<png::EncodingError> for RenderError {
+    fn from(e: png::EncodingError) -> RenderError {
+        RenderError::PNGError(e)
+    }
+}
+
+impl From<io::Error> for RenderError {
+    fn from(e: io::Error) -> RenderError {
+        RenderError::IOError(e)
+    }
+}
+
+impl From<gif::EncodingError> for RenderError {
+    fn from(e: gif::EncodingError) -> RenderError {
+        RenderError::GifError(e)
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::SVGError(e) => write!(f, "Error making the SVG: {}", e),
+            RenderError::PNGError(e) => write!(f, "Error making the PNG: {}", e),
+            RenderError::IOError(e) => write!(f, "I/O error: {}", e),
+            RenderError::GifError(e) => write!(f, "Error making the GIF: {}", e),
+            RenderError::MathError(e) => write!(f, "Compilation error:\n{}", e),
+            RenderError::NoImgError() => write!(f, "No image"),
+        }
+    }
+}
+
+impl StdErr for RenderError {
+    fn source(&self) -> Option<&(dyn StdErr + 'static)> {
+        match self {
+            RenderError::SVGError(inner) => Some(inner),
+            RenderError::PNGError(inner) => Some(inner),
+            RenderError::IOError(inner) => Some(inner),
+            RenderError::GifError(inner) => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+pub const SCALE: u32 = 8;
+pub const MIN_SCALE: u32 = 2;
+pub const MAX_SCALE: u32 = 16;
+/// Longest side of any rendered image, in pixels
+pub const MAX_RASTER_SIZE: u32 = 4096;
+
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum MathText {
+    Latex(String),
+    AsciiMath(String)
+}
+
+impl MathText {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MathText::Latex(s) => s,
+            MathText::AsciiMath(s) => s,
+        }
+    }
+}
+
+/// How long each stage of a render took
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RenderTimings {
+    pub compile: Duration,
+    pub svg: Duration,
+    pub raster: Duration,
+    pub encode: Duration,
+    /// The SVG from an earlier render was reused
+    pub cached: bool,
+}
+
+impl RenderTimings {
+    pub fn total(&self) -> Duration {
+        self.compile + self.svg + self.raster + self.encode
+    }
+
+    /// Something like `compile 412ms · svg 38ms · raster 21ms · png 9ms`
+    pub fn footer(&self) -> String {
+        let compiled = if self.cached {
+            String::from("cached svg")
+        } else {
+            format!("compile {}ms · svg {}ms", self.compile.as_millis(), self.svg.as_millis())
+        };
+        format!("{} · raster {}ms · png {}ms", compiled, self.raster.as_millis(), self.encode.as_millis())
+    }
+}
+
+const PREAMBLE: [&str; 12] = [
+    r"\documentclass[preview,margin=1pt]{standalone}",
+    r"\usepackage[utf8]{inputenc}",
+    r"\usepackage{mathtools}",
+    r"\usepackage{siunitx}",
+    r"\usepackage[version=4]{mhchem}",
+    r"\usepackage{amsmath}",
+    r"\usepackage{amssymb}",
+    r"\usepackage{mathrsfs}",
+    r"\usepackage{physics}",
+    r"\usepackage{tikz-cd}",
+    r"\usepackage{microtype}",
+    r"\usepackage{xcolor}",
+];
+
+/// The full document a LaTeX snippet gets compiled as
+pub fn latex_doc(body: &str, color: &str) -> String {
+    format!("{} \\begin{{document}} \\color{{{}}} {} \\end{{document}}", PREAMBLE.join(" "), color, body)
+}
+
+/// The same document as `latex_doc`, laid out to be read and built by hand
+pub fn latex_file(body: &str, color: &str) -> String {
+    format!("{}\n\n\\begin{{document}}\n\\color{{{}}}\n{}\n\\end{{document}}\n", PREAMBLE.join("\n"), color, body)
+}
+
+const SVG_CACHE_SIZE: usize = 64;
+const TEX_TIMEOUT: Duration = Duration::from_secs(30);
+/// Documents get previews of this many pages at most
+pub const MAX_PAGES: usize = 30;
+
+lazy_static! {
+    /// Recently compiled SVGs, keyed by what went into the compiler
+    static ref SVG_CACHE: Mutex<VecDeque<(MathText, Vec<u8>)>> = Mutex::new(VecDeque::with_capacity(SVG_CACHE_SIZE));
+}
+
+/// Runs a TeX engine in `dir` on a document or a file in it. There's no shell in between, no shell escape,
+/// no reading or writing files outside `dir`, and it gets killed if it runs for too long
+async fn run_tex(program: &str, dir: &Path, input: &str) -> Result<Output, RenderError> {
+    let tex_cli = Command::new(program)
+        .arg("-interaction=nonstopmode")
+        .arg("-no-shell-escape")
+        .arg("-jobname=texput")
+        .arg(input)
+        .current_dir(dir)
+        .env("openin_any", "p")
+        .env("openout_any", "p")
+        .kill_on_drop(true)
+        .output();
+
+    match tokio::time::timeout(TEX_TIMEOUT, tex_cli).await {
+        Ok(o) => Ok(o?),
+        Err(_) => Err(RenderError::MathError(format!("Gave up after {} seconds", TEX_TIMEOUT.as_secs()))),
+    }
+}
+
+/// Compiles LaTeX or AsciiMath to a white SVG, reusing a recent compile of the same source.
+/// LaTeX goes in as is, so anything like `unicode_to_latex` has to be done beforehand
+pub async fn compile_svg(source: &MathText, timings: &mut RenderTimings) -> Result<Vec<u8>, RenderError> {
+    let cached = SVG_CACHE.lock().unwrap().iter().find(|(s, _)| s == source).map(|(_, svg)| svg.clone());
+    if let Some(svg) = cached {
+        timings.cached = true;
+        return Ok(svg)
+    }
+
+    let start = Instant::now();
+    let svg = match source {
+        MathText::Latex(s) => {
+            let tex_dir = tempfile::TempDir::new()?;
+
+            let dvitex_cli = run_tex("latex", tex_dir.path(), &latex_doc(s, "white")).await?;
+
+            if !(dvitex_cli.status.success()) {
+                let err = String::from_utf8(dvitex_cli.stdout).unwrap();
+                let useless = Regex::new(r"(?m)(^\(.+$\n)|(^This is .*$\n)|(^Document Class.*$\n)|(^No file.*$\n)|(^.* written on .*\.$\n)|(^\[1\].*$\n)|(^For additional .*$\n)|(^LaTeX2e .*$\n)|(^Preview.*$\n)|(^L3.*$\n)|(^ restricted \\write18 enabled\.$\n)|(^entering extended mode$\n)|(^dalone$\n)|(^.*\.dict\).*$\n)|(^*./usr/share.*$\n)|(^.*\.tex.*$\n)|(^[()]+$\n)").unwrap();
+                let err = useless.replace_all(&err, "").to_string();
+
+                return Err(RenderError::MathError(err));
+            }
+
+            timings.compile = start.elapsed();
+            let start = Instant::now();
+
+            let dvisvg_cli = Command::new("dvisvgm")
+                .args(&["--page=1-", "-n", "--bbox=2pt", "-s"])
+                .arg(tex_dir.path().join("texput.dvi"))
+                .output()
+                .await?;
+
+            if !(dvisvg_cli.status.success()) {
+                return Err(RenderError::MathError(String::from_utf8(dvisvg_cli.stderr).unwrap()))
+            }
+
+            timings.svg = start.elapsed();
+            dvisvg_cli.stdout
+        },
+        MathText::AsciiMath(s) => {
+            let mjax_cli = Command::new(format!("{}/node_modules/.bin/am2svg", env!("HOME")))
+                .arg(s)
+                .output()
+                .await?;
+
+            if !(mjax_cli.status.success()) {
+                return Err(RenderError::MathError(String::from_utf8(mjax_cli.stderr).unwrap()));
+            }
+
+            let svg_raw = String::from_utf8(mjax_cli.stdout).unwrap();
+            let color_replacer = Regex::new("currentColor").unwrap();
+            let svg_raw = color_replacer.replace_all(&svg_raw, "white");
+
+            // am2svg goes straight to SVG, so it's all compile time
+            timings.compile = start.elapsed();
+            svg_raw.as_bytes().to_vec()
+        },
+    };
+
+    let mut cache = SVG_CACHE.lock().unwrap();
+    if cache.len() >= SVG_CACHE_SIZE {
+        cache.pop_front();
+    }
+    cache.push_back((source.clone(), svg.clone()));
+
+    Ok(svg)
+}
+
+/// Renders an SVG on a background, `scale` times its size but at most `MAX_RASTER_SIZE` pixels across
+pub fn raster_pixmap(svg: &[u8], scale: u32, background: Color) -> Result<tiny_skia::Pixmap, RenderError> {
+    let mut opt = usvg::Options::default();
+    opt.fontdb.load_system_fonts();
+    opt.fontdb.set_generic_families();
+
+    let svg_tree = usvg::Tree::from_data(svg, &opt)?;
+    let size = svg_tree.svg_node().size.to_screen_size();
+
+    // The SVG size comes from user input (page sizes, rules, long equations), so clamp the longer side
+    let fit = if size.width().max(size.height()).saturating_mul(scale) <= MAX_RASTER_SIZE {
+        usvg::FitTo::Zoom(scale as f32)
+    } else if size.width() >= size.height() {
+        usvg::FitTo::Width(MAX_RASTER_SIZE)
+    } else {
+        usvg::FitTo::Height(MAX_RASTER_SIZE)
+    };
+    let pixmap_size = fit.fit_to(size).ok_or_else(RenderError::NoImgError)?;
+    let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height()).ok_or_else(RenderError::NoImgError)?;
+    pixmap.fill(background);
+
+    if resvg::render(&svg_tree, fit, pixmap.as_mut()).is_none() {
+        return Err(RenderError::NoImgError());
+    }
+
+    Ok(pixmap)
+}
+
+/// Renders an SVG to a PNG on black, `scale` times its size
+pub fn rasterize(svg: &[u8], scale: u32, timings: &mut RenderTimings) -> Result<Vec<u8>, RenderError> {
+    let start = Instant::now();
+    let pixmap = raster_pixmap(svg, scale, Color::BLACK)?;
+    timings.raster = start.elapsed();
+
+    let start = Instant::now();
+    let png = pixmap.encode_png()?;
+    timings.encode = start.elapsed();
+
+    Ok(png)
+}
+
+/// An endlessly looping GIF of the frames, each shown for its delay in hundredths of a second.
+/// Frames are drawn in the top left corner of a canvas that fits the largest of them
+pub fn encode_gif(frames: &[(tiny_skia::Pixmap, u16)]) -> Result<Vec<u8>, RenderError> {
+    let width = frames.iter().map(|(p, _)| p.width()).max().unwrap_or(1);
+    let height = frames.iter().map(|(p, _)| p.height()).max().unwrap_or(1);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(RenderError::NoImgError())
+    }
+
+    let mut out = vec![];
+    {
+        let mut encoder = gif::Encoder::new(&mut out, width as u16, height as u16, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        for (pixmap, delay) in frames {
+            // The pixmaps are opaque, so their premultiplied colours can go in as they are
+            let mut canvas: Vec<u8> = [0, 0, 0, 255].repeat((width * height) as usize);
+            let row = (pixmap.width() * 4) as usize;
+            for (y, line) in pixmap.data().chunks(row).enumerate() {
+                let at = y * (width * 4) as usize;
+                canvas[at..at + row].copy_from_slice(line);
+            }
+
+            let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut canvas, 10);
+            frame.delay = *delay;
+            encoder.write_frame(&frame)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compiles LaTeX to a PDF with black text, for printing and downloads
+pub async fn compile_pdf(latex: &str) -> Result<Vec<u8>, RenderError> {
+    let tex_dir = tempfile::TempDir::new()?;
+
+    let pdftex_cli = run_tex("pdflatex", tex_dir.path(), &latex_doc(latex, "black")).await?;
+
+    if !(pdftex_cli.status.success()) {
+        return Err(RenderError::MathError(String::from_utf8(pdftex_cli.stdout).unwrap()));
+    }
+
+    Ok(tokio::fs::read(tex_dir.path().join("texput.pdf")).await?)
+}
+
+/// A compiled document, with an SVG of each page for previews
+pub struct Document {
+    pub pdf: Vec<u8>,
+    pub pages: Vec<Vec<u8>>,
+}
+
+/// The `!` error lines and where they happened, out of a whole TeX log
+fn tex_errors(log: &str) -> String {
+    let lines: Vec<&str> = log.lines().collect();
+    let errors: Vec<&str> = lines.iter().enumerate()
+        .filter(|(_, l)| l.starts_with('!'))
+        .flat_map(|(i, _)| lines[i..].iter().take(3).cloned())
+        .collect();
+
+    if errors.is_empty() {
+        lines[lines.len().saturating_sub(10)..].join("\n")
+    } else {
+        errors.join("\n")
+    }
+}
+
+/// Compiles a whole document to a PDF, and its pages to SVGs with `dvisvgm`
+pub async fn compile_document(source: &str) -> Result<Document, RenderError> {
+    lazy_static! {
+        static ref PAGE_RE: Regex = Regex::new(r"^page-(?P<n>\d+)\.svg$").unwrap();
+    }
+
+    let tex_dir = tempfile::TempDir::new()?;
+    tokio::fs::write(tex_dir.path().join("texput.tex"), source).await?;
+
+    // Cross references and numbered theorems need a second run to settle
+    let mut pdftex_cli = run_tex("pdflatex", tex_dir.path(), "texput.tex").await?;
+    if pdftex_cli.status.success() && String::from_utf8_lossy(&pdftex_cli.stdout).contains("Rerun") {
+        pdftex_cli = run_tex("pdflatex", tex_dir.path(), "texput.tex").await?;
+    }
+    if !(pdftex_cli.status.success()) {
+        return Err(RenderError::MathError(tex_errors(&String::from_utf8_lossy(&pdftex_cli.stdout))));
+    }
+    let pdf = tokio::fs::read(tex_dir.path().join("texput.pdf")).await?;
+
+    // The previews come out of the DVI, the same way snippets do
+    let dvitex_cli = run_tex("latex", tex_dir.path(), "texput.tex").await?;
+    if !(dvitex_cli.status.success()) {
+        return Err(RenderError::MathError(tex_errors(&String::from_utf8_lossy(&dvitex_cli.stdout))));
+    }
+
+    let dvisvg_cli = Command::new("dvisvgm")
+        .arg(format!("--page=1-{}", MAX_PAGES))
+        .args(&["-n", "--bbox=papersize", "-o", "page-%p.svg", "texput.dvi"])
+        .current_dir(tex_dir.path())
+        .output()
+        .await?;
+    if !(dvisvg_cli.status.success()) {
+        return Err(RenderError::MathError(String::from_utf8(dvisvg_cli.stderr).unwrap()))
+    }
+
+    let mut pages: Vec<(usize, Vec<u8>)> = vec![];
+    for entry in std::fs::read_dir(tex_dir.path())? {
+        let path = entry?.path();
+        let n = match path.file_name().and_then(|f| f.to_str()).and_then(|f| PAGE_RE.captures(f)) {
+            Some(c) => c["n"].parse::<usize>().unwrap_or(0),
+            None => {continue}
+        };
+        pages.push((n, tokio::fs::read(&path).await?));
+    }
+    pages.sort_by_key(|(n, _)| *n);
+
+    Ok(Document {
+        pdf,
+        pages: pages.into_iter().map(|(_, p)| p).collect(),
+    })
+}